@@ -0,0 +1,230 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+pub const IMAGE_WIDTH: usize = 128;
+pub const IMAGE_HEIGHT: usize = 112;
+
+const REGISTER_COUNT: usize = 0x36;
+const MATRIX_BEGIN: usize = 0x06;
+// Captured tiles land in RAM bank 0 after the first 256 bytes
+const IMAGE_RAM_BEGIN: usize = 0x0100;
+
+// Base capture time in machine cycles, before exposure
+const CAPTURE_CYCLES: u32 = 32_446;
+
+// Game Boy Camera (Pocket Camera): MBC with 128 KiB RAM and a register window
+// driving the image sensor. Captures dither a host-supplied image into tiles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PocketCamera {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_write_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+    registers_mapped: bool, // 0xA000-0xBFFF accesses the sensor registers
+    registers: [u8; REGISTER_COUNT],
+    capture_cycles: u32, // remaining until the current capture finishes
+    image: Vec<u8>,      // host image, one byte per pixel, 0 is black
+}
+
+impl PocketCamera {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        PocketCamera {
+            rom,
+            ram: vec![0; ram_size],
+            ram_write_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            registers_mapped: false,
+            registers: [0; REGISTER_COUNT],
+            capture_cycles: 0,
+            image: vec![0xFF; IMAGE_WIDTH * IMAGE_HEIGHT],
+        }
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        banked_ram_index(&self.ram, self.ram_bank as usize, addr as usize - 0xA000)
+    }
+
+    fn capturing(&self) -> bool {
+        self.capture_cycles > 0
+    }
+
+    // Longer exposures take longer; N mode skips part of the readout
+    fn capture_time(&self) -> u32 {
+        let exposure = (self.registers[2] as u32) << 8 | self.registers[3] as u32;
+        let n_mode = self.registers[1] & 0x80 != 0;
+        CAPTURE_CYCLES + if n_mode { 0 } else { 512 } + exposure * 16
+    }
+
+    // Thresholds each pixel against the 4x4 dither matrix and stores the
+    // result as 2bpp tiles, 16 tiles per row
+    fn finish_capture(&mut self) {
+        for y in 0..IMAGE_HEIGHT {
+            for x in 0..IMAGE_WIDTH {
+                let matrix = MATRIX_BEGIN + ((y % 4) * 4 + x % 4) * 3;
+                let thresholds = &self.registers[matrix..matrix + 3];
+                let value = self.image[y * IMAGE_WIDTH + x];
+                let color = if value < thresholds[0] {
+                    3
+                } else if value < thresholds[1] {
+                    2
+                } else if value < thresholds[2] {
+                    1
+                } else {
+                    0
+                };
+
+                let tile = (y / 8) * (IMAGE_WIDTH / 8) + x / 8;
+                let offset = IMAGE_RAM_BEGIN + tile * 16 + (y % 8) * 2;
+                let Some(low) = banked_ram_index(&self.ram, 0, offset) else {
+                    return;
+                };
+                let bit = 0x80 >> (x % 8);
+                for (plane, idx) in [low, low + 1].into_iter().enumerate() {
+                    if color >> plane & 0b1 != 0 {
+                        self.ram[idx] |= bit;
+                    } else {
+                        self.ram[idx] &= !bit;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Mapper for PocketCamera {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            // Bank 0 can be mapped here too
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ram_write_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = value & 0x3F,
+            0x4000..=0x5FFF => {
+                self.registers_mapped = value & 0x10 != 0;
+                self.ram_bank = value & 0x0F;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    // Only 0xA000 is readable in the register window: bit 0 is set while a
+    // capture is running
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.registers_mapped {
+            return match addr & 0x7F {
+                0x00 => (self.registers[0] & 0x06) | self.capturing() as u8,
+                _ => 0x00,
+            };
+        }
+        // RAM is readable without an enable, except while the sensor uses it
+        if self.capturing() {
+            return 0x00;
+        }
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if self.registers_mapped {
+            let register = (addr & 0x7F) as usize;
+            if register == 0x00 {
+                self.registers[0] = value & 0x07;
+                if value & 0b1 != 0 && !self.capturing() {
+                    self.capture_cycles = self.capture_time();
+                } else if value & 0b1 == 0 {
+                    // Clearing the start bit aborts the capture
+                    self.capture_cycles = 0;
+                }
+            } else if register < REGISTER_COUNT {
+                self.registers[register] = value;
+            }
+            return false;
+        }
+        if !self.ram_write_enabled || self.capturing() {
+            return false;
+        }
+        if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn set_camera_image(&mut self, image: &[u8]) {
+        let len = self.image.len().min(image.len());
+        self.image[..len].copy_from_slice(&image[..len]);
+    }
+
+    fn step(&mut self, cycles: u32) {
+        if !self.capturing() {
+            return;
+        }
+        self.capture_cycles = self.capture_cycles.saturating_sub(cycles);
+        if !self.capturing() {
+            self.registers[0] &= !0b1;
+            self.finish_capture();
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn capture_dithers_the_image_into_tiles() {
+        let mut camera = PocketCamera::new(numbered_rom(4), 0x20000);
+        // Pixels cycling through black to white along each row
+        let image = [0x00, 0x50, 0x90, 0xFF].repeat(IMAGE_WIDTH * IMAGE_HEIGHT / 4);
+        camera.set_camera_image(&image);
+
+        camera.write_rom(0x4000, 0x10);
+        camera.write_ram(0xA001, 0x80);
+        camera.write_ram(0xA002, 0x00);
+        camera.write_ram(0xA003, 0x10);
+        for register in (MATRIX_BEGIN..REGISTER_COUNT).step_by(3) {
+            camera.write_ram(0xA000 + register as u16, 0x40);
+            camera.write_ram(0xA001 + register as u16, 0x80);
+            camera.write_ram(0xA002 + register as u16, 0xC0);
+        }
+
+        camera.write_ram(0xA000, 0x01);
+        let time = camera.capture_time();
+        assert_eq!(time, CAPTURE_CYCLES + 0x10 * 16);
+        camera.step(time - 1);
+        assert_eq!(camera.read_ram(0xA000), 0x01);
+        camera.step(1);
+        assert_eq!(camera.read_ram(0xA000), 0x00);
+
+        // Colors 3, 2, 1, 0 repeating, as 2bpp tile rows
+        camera.write_rom(0x4000, 0x00);
+        assert_eq!(camera.read_ram(0xA0FF), 0x00);
+        for row in 0..8 {
+            assert_eq!(camera.read_ram(0xA100 + row * 2), 0xAA);
+            assert_eq!(camera.read_ram(0xA101 + row * 2), 0xCC);
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use super::CartridgeError;
+
+pub const HEADER_BEGIN: usize = 0x0100;
+pub const HEADER_END: usize = 0x014F;
+
+const TITLE_BEGIN: usize = 0x0134;
+const MANUFACTURER_CODE_BEGIN: usize = 0x013F;
+const CGB_FLAG: usize = 0x0143;
+const NEW_LICENSEE_CODE: usize = 0x0144;
+const SGB_FLAG: usize = 0x0146;
+const CARTRIDGE_TYPE: usize = 0x0147;
+const ROM_SIZE: usize = 0x0148;
+const RAM_SIZE: usize = 0x0149;
+const DESTINATION_CODE: usize = 0x014A;
+const OLD_LICENSEE_CODE: usize = 0x014B;
+const VERSION: usize = 0x014C;
+const HEADER_CHECKSUM: usize = 0x014D;
+const GLOBAL_CHECKSUM: usize = 0x014E;
+
+// Old licensee value meaning "use the new licensee code instead"
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MapperType {
+    RomOnly,
+    MBC1,
+    MBC2,
+    MBC3,
+    MBC5,
+    MBC6,
+    MBC7,
+    MMM01,
+    PocketCamera,
+    TAMA5,
+    HuC1,
+    HuC3,
+}
+
+// Decoded cartridge type byte (0x0147)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CartridgeType {
+    pub mapper: MapperType,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+impl CartridgeType {
+    fn new(mapper: MapperType) -> Self {
+        CartridgeType {
+            mapper,
+            ram: false,
+            battery: false,
+            timer: false,
+            rumble: false,
+        }
+    }
+
+    fn with_ram(mut self) -> Self {
+        self.ram = true;
+        self
+    }
+
+    fn with_battery(mut self) -> Self {
+        self.battery = true;
+        self
+    }
+
+    fn with_timer(mut self) -> Self {
+        self.timer = true;
+        self
+    }
+
+    fn with_rumble(mut self) -> Self {
+        self.rumble = true;
+        self
+    }
+
+    pub fn from_byte(byte: u8) -> Result<Self, CartridgeError> {
+        let kind = match byte {
+            0x00 => CartridgeType::new(MapperType::RomOnly),
+            0x01 => CartridgeType::new(MapperType::MBC1),
+            0x02 => CartridgeType::new(MapperType::MBC1).with_ram(),
+            0x03 => CartridgeType::new(MapperType::MBC1)
+                .with_ram()
+                .with_battery(),
+            0x05 => CartridgeType::new(MapperType::MBC2),
+            0x06 => CartridgeType::new(MapperType::MBC2).with_battery(),
+            0x08 => CartridgeType::new(MapperType::RomOnly).with_ram(),
+            0x09 => CartridgeType::new(MapperType::RomOnly)
+                .with_ram()
+                .with_battery(),
+            0x0B => CartridgeType::new(MapperType::MMM01),
+            0x0C => CartridgeType::new(MapperType::MMM01).with_ram(),
+            0x0D => CartridgeType::new(MapperType::MMM01)
+                .with_ram()
+                .with_battery(),
+            0x0F => CartridgeType::new(MapperType::MBC3)
+                .with_timer()
+                .with_battery(),
+            0x10 => CartridgeType::new(MapperType::MBC3)
+                .with_timer()
+                .with_ram()
+                .with_battery(),
+            0x11 => CartridgeType::new(MapperType::MBC3),
+            0x12 => CartridgeType::new(MapperType::MBC3).with_ram(),
+            0x13 => CartridgeType::new(MapperType::MBC3)
+                .with_ram()
+                .with_battery(),
+            0x19 => CartridgeType::new(MapperType::MBC5),
+            0x1A => CartridgeType::new(MapperType::MBC5).with_ram(),
+            0x1B => CartridgeType::new(MapperType::MBC5)
+                .with_ram()
+                .with_battery(),
+            0x1C => CartridgeType::new(MapperType::MBC5).with_rumble(),
+            0x1D => CartridgeType::new(MapperType::MBC5)
+                .with_rumble()
+                .with_ram(),
+            0x1E => CartridgeType::new(MapperType::MBC5)
+                .with_rumble()
+                .with_ram()
+                .with_battery(),
+            // MBC6 carts always have battery RAM alongside their flash
+            0x20 => CartridgeType::new(MapperType::MBC6)
+                .with_ram()
+                .with_battery(),
+            0x22 => CartridgeType::new(MapperType::MBC7)
+                .with_rumble()
+                .with_ram()
+                .with_battery(),
+            0xFC => CartridgeType::new(MapperType::PocketCamera)
+                .with_ram()
+                .with_battery(),
+            0xFD => CartridgeType::new(MapperType::TAMA5),
+            0xFE => CartridgeType::new(MapperType::HuC3)
+                .with_timer()
+                .with_ram()
+                .with_battery(),
+            0xFF => CartridgeType::new(MapperType::HuC1)
+                .with_ram()
+                .with_battery(),
+            _ => return Err(CartridgeError::UnsupportedCartridgeType(byte)),
+        };
+        Ok(kind)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CgbSupport {
+    None,       // DMG only
+    Compatible, // runs on both DMG and CGB
+    Only,       // CGB only
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_support: CgbSupport,
+    pub sgb_support: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub rom_size: usize, // in bytes
+    pub ram_size: usize, // in bytes
+    pub japanese: bool,  // destination code
+    pub old_licensee_code: u8,
+    pub new_licensee_code: [u8; 2],
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() <= HEADER_END {
+            return Err(CartridgeError::TooSmall(rom.len()));
+        }
+
+        let cgb_support = match rom[CGB_FLAG] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Compatible,
+            _ => CgbSupport::None,
+        };
+
+        // CGB-era headers shorten the title to make room for the manufacturer
+        // code and CGB flag
+        let (title_end, manufacturer_code) = match cgb_support {
+            CgbSupport::None => (CGB_FLAG + 1, None),
+            _ => {
+                let code = &rom[MANUFACTURER_CODE_BEGIN..CGB_FLAG];
+                if code.iter().all(|c| c.is_ascii_alphanumeric()) {
+                    let code = String::from_utf8_lossy(code).into_owned();
+                    (MANUFACTURER_CODE_BEGIN, Some(code))
+                } else {
+                    (CGB_FLAG, None)
+                }
+            }
+        };
+        let title = rom[TITLE_BEGIN..title_end]
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as char)
+            .collect::<String>()
+            .trim_end()
+            .to_string();
+
+        let rom_size_code = rom[ROM_SIZE];
+        let rom_size = match rom_size_code {
+            0x00..=0x08 => (32 * 1024) << rom_size_code,
+            _ => return Err(CartridgeError::InvalidRomSize(rom_size_code)),
+        };
+
+        let ram_size_code = rom[RAM_SIZE];
+        let ram_size = match ram_size_code {
+            0x00 => 0,
+            // Listed in some unofficial docs but never used by a licensed game
+            0x01 => 2 * 1024,
+            0x02 => 8 * 1024,
+            0x03 => 32 * 1024,
+            0x04 => 128 * 1024,
+            0x05 => 64 * 1024,
+            _ => return Err(CartridgeError::InvalidRamSize(ram_size_code)),
+        };
+
+        let header = CartridgeHeader {
+            title,
+            manufacturer_code,
+            cgb_support,
+            sgb_support: rom[SGB_FLAG] == 0x03,
+            cartridge_type: CartridgeType::from_byte(rom[CARTRIDGE_TYPE])?,
+            rom_size_code,
+            ram_size_code,
+            rom_size,
+            ram_size,
+            japanese: rom[DESTINATION_CODE] == 0x00,
+            old_licensee_code: rom[OLD_LICENSEE_CODE],
+            new_licensee_code: [rom[NEW_LICENSEE_CODE], rom[NEW_LICENSEE_CODE + 1]],
+            version: rom[VERSION],
+            header_checksum: rom[HEADER_CHECKSUM],
+            global_checksum: (rom[GLOBAL_CHECKSUM] as u16) << 8 | rom[GLOBAL_CHECKSUM + 1] as u16,
+        };
+
+        let actual = header_checksum(rom);
+        if actual != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksumMismatch {
+                expected: header.header_checksum,
+                actual,
+            });
+        }
+
+        Ok(header)
+    }
+
+    // Publisher code, either the old one-byte code or the two-character new one
+    pub fn licensee_code(&self) -> String {
+        if self.old_licensee_code == USE_NEW_LICENSEE_CODE {
+            String::from_utf8_lossy(&self.new_licensee_code).into_owned()
+        } else {
+            format!("{:02X}", self.old_licensee_code)
+        }
+    }
+}
+
+// Checksum over 0x0134-0x014C, verified by the boot ROM
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_BEGIN..HEADER_CHECKSUM]
+        .iter()
+        .fold(0u8, |acc, &byte| acc.wrapping_sub(byte).wrapping_sub(1))
+}
+
+// Sum of the 16 title bytes, which the CGB boot ROM uses to pick a palette
+// for DMG games
+pub fn title_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_BEGIN..=CGB_FLAG]
+        .iter()
+        .fold(0u8, |acc, &byte| acc.wrapping_add(byte))
+}
+
+// Sum of every ROM byte except the global checksum itself. Hardware never
+// checks this, so a mismatch doesn't prevent a game from running.
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(addr, _)| *addr != GLOBAL_CHECKSUM && *addr != GLOBAL_CHECKSUM + 1)
+        .fold(0u16, |acc, (_, &byte)| acc.wrapping_add(byte as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses a 32 KiB ROM of zeros with `bytes` written at their addresses
+    // and a correct header checksum
+    fn parse(bytes: &[(usize, &[u8])]) -> Result<CartridgeHeader, CartridgeError> {
+        let mut rom = vec![0; 0x8000];
+        for (addr, data) in bytes {
+            rom[*addr..*addr + data.len()].copy_from_slice(data);
+        }
+        rom[HEADER_CHECKSUM] = header_checksum(&rom);
+        CartridgeHeader::parse(&rom)
+    }
+
+    #[test]
+    fn title_and_manufacturer_code_depend_on_the_cgb_flag() {
+        let title = b"POKEMON_GLDAAUE";
+        let header = parse(&[(TITLE_BEGIN, title)]).unwrap();
+        assert_eq!(header.title, "POKEMON_GLDAAUE");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.cgb_support, CgbSupport::None);
+
+        // The last 4 title bytes become the manufacturer code
+        let header = parse(&[(TITLE_BEGIN, title), (CGB_FLAG, &[0x80])]).unwrap();
+        assert_eq!(header.title, "POKEMON_GLD");
+        assert_eq!(header.manufacturer_code.as_deref(), Some("AAUE"));
+        assert_eq!(header.cgb_support, CgbSupport::Compatible);
+
+        // ...unless they aren't a code, then only the CGB flag is cut off
+        let header = parse(&[(TITLE_BEGIN, b"TETRIS DX  \0"), (CGB_FLAG, &[0xC0])]).unwrap();
+        assert_eq!(header.title, "TETRIS DX");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.cgb_support, CgbSupport::Only);
+    }
+
+    #[test]
+    fn sgb_flag_and_licensee_code() {
+        let header = parse(&[(SGB_FLAG, &[0x03]), (OLD_LICENSEE_CODE, &[0x01])]).unwrap();
+        assert!(header.sgb_support);
+        assert_eq!(header.licensee_code(), "01");
+
+        let header = parse(&[
+            (SGB_FLAG, &[0x01]),
+            (NEW_LICENSEE_CODE, b"01"),
+            (OLD_LICENSEE_CODE, &[USE_NEW_LICENSEE_CODE]),
+        ])
+        .unwrap();
+        assert!(!header.sgb_support);
+        assert_eq!(header.licensee_code(), "01");
+    }
+
+    #[test]
+    fn rom_and_ram_size_codes() {
+        let rom_sizes = [(0x00, 0x8000), (0x01, 0x10000), (0x05, 0x100000)];
+        for (code, size) in rom_sizes {
+            let header = parse(&[(ROM_SIZE, &[code])]).unwrap();
+            assert_eq!((header.rom_size_code, header.rom_size), (code, size));
+        }
+        assert_eq!(parse(&[(ROM_SIZE, &[0x08])]).unwrap().rom_size, 0x800000);
+        assert!(matches!(
+            parse(&[(ROM_SIZE, &[0x09])]),
+            Err(CartridgeError::InvalidRomSize(0x09))
+        ));
+
+        let ram_sizes = [0, 0x800, 0x2000, 0x8000, 0x20000, 0x10000];
+        for (code, size) in ram_sizes.into_iter().enumerate() {
+            let header = parse(&[(RAM_SIZE, &[code as u8])]).unwrap();
+            assert_eq!(header.ram_size, size);
+        }
+        assert!(matches!(
+            parse(&[(RAM_SIZE, &[0x06])]),
+            Err(CartridgeError::InvalidRamSize(0x06))
+        ));
+    }
+
+    #[test]
+    fn header_checksum_mismatch_is_an_error() {
+        let mut rom = vec![0; 0x8000];
+        rom[HEADER_CHECKSUM] = header_checksum(&rom);
+        assert!(CartridgeHeader::parse(&rom).is_ok());
+
+        rom[TITLE_BEGIN] = b'A';
+        let expected = rom[HEADER_CHECKSUM];
+        assert!(matches!(
+            CartridgeHeader::parse(&rom),
+            Err(CartridgeError::HeaderChecksumMismatch { expected: e, actual })
+                if e == expected && actual == expected.wrapping_sub(b'A')
+        ));
+    }
+
+    #[test]
+    fn global_checksum_skips_its_own_bytes() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0000] = 0xFF;
+        rom[0x7FFF] = 0x02;
+        rom[GLOBAL_CHECKSUM] = 0x12;
+        rom[GLOBAL_CHECKSUM + 1] = 0x34;
+        assert_eq!(global_checksum(&rom), 0x0101);
+    }
+}
@@ -0,0 +1,95 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+// Value read from the IR port when no light is received
+const IR_NO_LIGHT: u8 = 0xC0;
+
+// Hudson HuC1: MBC1-like banking plus an infrared LED and receiver that can be
+// mapped over the RAM area
+#[derive(Clone, Debug, PartialEq)]
+pub struct HuC1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ir_mode: bool, // 0xA000-0xBFFF accesses the IR port instead of RAM
+    ir_led: bool,
+    rom_bank: u8,
+    ram_bank: u8,
+}
+
+impl HuC1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        HuC1 {
+            rom,
+            ram: vec![0; ram_size],
+            ir_mode: false,
+            ir_led: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        banked_ram_index(&self.ram, self.ram_bank as usize, addr as usize - 0xA000)
+    }
+}
+
+impl Mapper for HuC1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ir_mode = value & 0x0F == 0x0E,
+            0x2000..=0x3FFF => {
+                self.rom_bank = value & 0x3F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            }
+            0x4000..=0x5FFF => self.ram_bank = value & 0x03,
+            _ => {}
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.ir_mode {
+            return IR_NO_LIGHT;
+        }
+        // RAM is readable without an explicit enable
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if self.ir_mode {
+            self.ir_led = value & 0b1 != 0;
+        } else if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
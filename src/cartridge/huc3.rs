@@ -0,0 +1,319 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+use super::rtc::{unix_time, RtcClock};
+
+// Machine cycles per minute of emulated time
+const CYCLES_PER_MINUTE: u32 = 60 * 1_048_576;
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+// Value read from the IR port when no light is received
+const IR_NO_LIGHT: u8 = 0xC0;
+
+// Minutes (u32), days (u32) and a UNIX timestamp (u64), little endian
+const CLOCK_FOOTER_SIZE: usize = 16;
+
+// What 0xA000-0xBFFF is connected to, selected through 0x0000-0x1FFF
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    RamReadOnly,
+    Ram,
+    RtcCommand,   // writes send a command to the clock chip
+    RtcResponse,  // reads return the clock chip's last result
+    RtcSemaphore, // reads 1 when the clock chip is ready
+    Ir,
+}
+
+// Hudson HuC3: ROM/RAM banking plus a clock chip counting minutes and days,
+// driven through a nibble-wide command interface, and an IR port
+#[derive(Clone, Debug, PartialEq)]
+pub struct HuC3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mode: Mode,
+    rom_bank: u8,
+    ram_bank: u8,
+    ir_led: bool,
+
+    clock: RtcClock,
+    minutes: u16, // minute of the day
+    days: u16,    // 12-bit day counter
+    subminute_cycles: u32,
+    last_sync: u64,
+    clock_memory: [u8; 256], // nibble-wide scratch memory of the clock chip
+    access_index: u8,
+    response: u8,
+}
+
+impl HuC3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, clock: RtcClock) -> Self {
+        HuC3 {
+            rom,
+            ram: vec![0; ram_size],
+            mode: Mode::RamReadOnly,
+            rom_bank: 1,
+            ram_bank: 0,
+            ir_led: false,
+            clock,
+            minutes: 0,
+            days: 0,
+            subminute_cycles: 0,
+            last_sync: unix_time(),
+            clock_memory: [0; 256],
+            access_index: 0,
+            response: 0,
+        }
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        banked_ram_index(&self.ram, self.ram_bank as usize, addr as usize - 0xA000)
+    }
+
+    // Minute of the day and day counter `minutes` after the current time
+    fn minutes_after(&self, minutes: u64) -> (u16, u16) {
+        let total = self.minutes as u64 + minutes;
+        let days = (self.days as u64 + total / MINUTES_PER_DAY as u64) & 0xFFF;
+        ((total % MINUTES_PER_DAY as u64) as u16, days as u16)
+    }
+
+    fn advance_minutes(&mut self, minutes: u64) {
+        (self.minutes, self.days) = self.minutes_after(minutes);
+    }
+
+    // Minutes, days and last sync time caught up with the host clock, keeping
+    // partial minutes
+    fn synced(&self) -> (u16, u16, u64) {
+        if self.clock != RtcClock::WallClock {
+            return (self.minutes, self.days, self.last_sync);
+        }
+        let minutes = unix_time().saturating_sub(self.last_sync) / 60;
+        let (minute, days) = self.minutes_after(minutes);
+        (minute, days, self.last_sync + minutes * 60)
+    }
+
+    fn sync(&mut self) {
+        (self.minutes, self.days, self.last_sync) = self.synced();
+    }
+
+    // Commands are a 4-bit opcode in the high nibble and an argument in the low
+    fn command(&mut self, value: u8) {
+        let argument = value & 0x0F;
+        match value >> 4 {
+            // Read the nibble at the access index, then advance
+            0x1 => {
+                self.response = self.clock_memory[self.access_index as usize];
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            // Write a nibble at the access index, then advance
+            0x3 => {
+                self.clock_memory[self.access_index as usize] = argument;
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            0x4 => self.access_index = (self.access_index & 0xF0) | argument,
+            0x5 => self.access_index = (self.access_index & 0x0F) | (argument << 4),
+            0x6 => match argument {
+                // Copy the current time into scratch memory 0x00-0x05
+                0x0 => {
+                    self.sync();
+                    for idx in 0..3 {
+                        self.clock_memory[idx] = ((self.minutes >> (idx * 4)) & 0xF) as u8;
+                        self.clock_memory[idx + 3] = ((self.days >> (idx * 4)) & 0xF) as u8;
+                    }
+                }
+                // Set the time from scratch memory 0x00-0x05
+                0x1 => {
+                    self.sync();
+                    let nibbles = |base: usize| {
+                        (0..3).fold(0u16, |acc, idx| {
+                            acc | (self.clock_memory[base + idx] as u16) << (idx * 4)
+                        })
+                    };
+                    self.minutes = nibbles(0) % MINUTES_PER_DAY;
+                    self.days = nibbles(3);
+                    self.subminute_cycles = 0;
+                }
+                // Status query, the chip always reports ready
+                0x2 => self.response = 0x1,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for HuC3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => {
+                self.mode = match value & 0x0F {
+                    0x0A => Mode::Ram,
+                    0x0B => Mode::RtcCommand,
+                    0x0C => Mode::RtcResponse,
+                    0x0D => Mode::RtcSemaphore,
+                    0x0E => Mode::Ir,
+                    _ => Mode::RamReadOnly,
+                }
+            }
+            0x2000..=0x3FFF => self.rom_bank = value & 0x7F,
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {}
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.mode {
+            Mode::Ram | Mode::RamReadOnly => match self.ram_index(addr) {
+                Some(idx) => self.ram[idx],
+                None => 0xFF,
+            },
+            Mode::RtcCommand => 0xFF,
+            Mode::RtcResponse => 0x80 | self.response,
+            Mode::RtcSemaphore => 0x01,
+            Mode::Ir => IR_NO_LIGHT,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        match self.mode {
+            Mode::Ram => {
+                if let Some(idx) = self.ram_index(addr) {
+                    self.ram[idx] = value;
+                    return true;
+                }
+                false
+            }
+            Mode::RtcCommand => {
+                self.command(value);
+                // Scratch memory isn't saved, only setting the time changes the clock footer
+                value == 0x61
+            }
+            Mode::Ir => {
+                self.ir_led = value & 0b1 != 0;
+                false
+            }
+            Mode::RamReadOnly | Mode::RtcResponse | Mode::RtcSemaphore => false,
+        }
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn ir_led(&self) -> bool {
+        self.ir_led
+    }
+
+    fn step(&mut self, cycles: u32) {
+        if self.clock != RtcClock::Cycles {
+            return;
+        }
+        self.subminute_cycles += cycles;
+        while self.subminute_cycles >= CYCLES_PER_MINUTE {
+            self.subminute_cycles -= CYCLES_PER_MINUTE;
+            self.advance_minutes(1);
+        }
+    }
+
+    // There's no common HuC3 clock format, so the clock is stored in a small
+    // footer of its own after RAM
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        let (minutes, days, last_sync) = self.synced();
+        data.extend_from_slice(&(minutes as u32).to_le_bytes());
+        data.extend_from_slice(&(days as u32).to_le_bytes());
+        data.extend_from_slice(&last_sync.to_le_bytes());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        let footer = data.get(self.ram.len()..).unwrap_or(&[]);
+        if footer.len() != CLOCK_FOOTER_SIZE {
+            return;
+        }
+        let minutes = u32::from_le_bytes(footer[0..4].try_into().unwrap());
+        let days = u32::from_le_bytes(footer[4..8].try_into().unwrap());
+        self.minutes = (minutes % MINUTES_PER_DAY as u32) as u16;
+        self.days = (days & 0xFFF) as u16;
+        self.last_sync = match self.clock {
+            RtcClock::WallClock => u64::from_le_bytes(footer[8..16].try_into().unwrap()),
+            RtcClock::Cycles => unix_time(),
+        };
+        self.sync();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    fn command(huc3: &mut HuC3, value: u8) -> bool {
+        huc3.write_rom(0x0000, 0x0B);
+        huc3.write_ram(0xA000, value)
+    }
+
+    fn response(huc3: &mut HuC3) -> u8 {
+        huc3.write_rom(0x0000, 0x0C);
+        huc3.read_ram(0xA000)
+    }
+
+    // Stores minute 0x123 of day 0x045 and sets the clock from it
+    fn set_time(huc3: &mut HuC3) {
+        command(huc3, 0x40);
+        command(huc3, 0x50);
+        for nibble in [0x3, 0x2, 0x1, 0x5, 0x4, 0x0] {
+            assert!(!command(huc3, 0x30 | nibble));
+        }
+        assert!(command(huc3, 0x61));
+    }
+
+    #[test]
+    fn clock_commands_set_and_read_the_time() {
+        let mut huc3 = HuC3::new(numbered_rom(4), 0x2000, RtcClock::Cycles);
+        set_time(&mut huc3);
+        huc3.step(CYCLES_PER_MINUTE);
+
+        assert!(!command(&mut huc3, 0x60));
+        command(&mut huc3, 0x40);
+        command(&mut huc3, 0x50);
+        let mut nibbles = vec![];
+        for _ in 0..6 {
+            command(&mut huc3, 0x10);
+            nibbles.push(response(&mut huc3));
+        }
+        assert_eq!(nibbles, [0x84, 0x82, 0x81, 0x85, 0x84, 0x80]);
+
+        // Status is always ready
+        command(&mut huc3, 0x62);
+        assert_eq!(response(&mut huc3), 0x81);
+    }
+
+    #[test]
+    fn clock_footer_round_trips() {
+        let mut huc3 = HuC3::new(numbered_rom(4), 0x2000, RtcClock::Cycles);
+        huc3.write_rom(0x0000, 0x0A);
+        huc3.write_ram(0xA000, 0x42);
+        set_time(&mut huc3);
+        let data = huc3.save_data();
+        assert_eq!(data.len(), 0x2000 + CLOCK_FOOTER_SIZE);
+        assert_eq!(data[0x2000..0x2008], [0x23, 0x01, 0, 0, 0x45, 0, 0, 0]);
+
+        let mut loaded = HuC3::new(numbered_rom(4), 0x2000, RtcClock::Cycles);
+        loaded.load_save_data(&data);
+        assert_eq!(loaded.read_ram(0xA000), 0x42);
+        assert_eq!((loaded.minutes, loaded.days), (0x123, 0x045));
+
+        // Saves without the footer leave the clock alone
+        let mut loaded = HuC3::new(numbered_rom(4), 0x2000, RtcClock::Cycles);
+        loaded.load_save_data(&data[..0x2000]);
+        assert_eq!((loaded.minutes, loaded.days), (0, 0));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+// Cartridge hardware sitting behind 0x0000-0x7FFF and 0xA000-0xBFFF. Writes to
+// the ROM area go to the mapper's control registers. Addresses are CPU addresses.
+// Writes return whether they changed anything that persists in the save.
+pub trait Mapper: fmt::Debug {
+    fn read_rom(&self, addr: u16) -> u8;
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool;
+    fn read_ram(&self, addr: u16) -> u8;
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool;
+
+    // Full ROM image
+    fn rom(&self) -> &[u8];
+
+    // Whether the cartridge's rumble motor is currently on
+    fn rumble(&self) -> bool {
+        false
+    }
+
+    // Whether the cartridge's infrared LED is lit, for hosts emulating a link partner
+    fn ir_led(&self) -> bool {
+        false
+    }
+
+    // Host-supplied accelerometer reading, in g along the sensor's X and Y axes
+    fn set_tilt(&mut self, _x: f32, _y: f32) {}
+
+    // Host-supplied 128x112 grayscale image (0 is black) for the camera sensor
+    fn set_camera_image(&mut self, _image: &[u8]) {}
+
+    // Advances any cartridge hardware clocked by the CPU (machine cycles)
+    fn step(&mut self, _cycles: u32) {}
+
+    // Battery-backed state in the common .sav layout: external RAM followed by
+    // any mapper-specific footer. Empty if nothing persists.
+    fn save_data(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn load_save_data(&mut self, _data: &[u8]) {}
+}
+
+// Byte at `offset` within ROM bank `bank`, wrapping the bank number to the ROM size
+pub fn banked_rom_byte(rom: &[u8], bank: usize, offset: usize) -> u8 {
+    let banks = (rom.len() / ROM_BANK_SIZE).max(1);
+    rom.get((bank % banks) * ROM_BANK_SIZE + offset)
+        .copied()
+        .unwrap_or(0xFF)
+}
+
+// Index into external RAM for `offset` within RAM bank `bank`, wrapping to the
+// RAM size. `None` when the cartridge has no RAM.
+pub fn banked_ram_index(ram: &[u8], bank: usize, offset: usize) -> Option<usize> {
+    if ram.is_empty() {
+        None
+    } else {
+        Some((bank * RAM_BANK_SIZE + offset) % ram.len())
+    }
+}
+
+// Copies a save into external RAM, ignoring anything that doesn't fit
+pub fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+// Plain 32 KiB ROM, optionally with up to 8 KiB of unbanked RAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        RomOnly {
+            rom,
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+
+    fn write_rom(&mut self, _addr: u16, _value: u8) -> bool {
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match banked_ram_index(&self.ram, 0, addr as usize - 0xA000) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if let Some(idx) = banked_ram_index(&self.ram, 0, addr as usize - 0xA000) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+// ROM whose banks are filled with the low byte of their bank number, except
+// for the second byte, which holds the high byte
+#[cfg(test)]
+pub fn numbered_rom(banks: usize) -> Vec<u8> {
+    (0..banks)
+        .flat_map(|bank| {
+            let mut data = vec![bank as u8; ROM_BANK_SIZE];
+            data[1] = (bank >> 8) as u8;
+            data
+        })
+        .collect()
+}
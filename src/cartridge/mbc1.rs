@@ -0,0 +1,204 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+const LOGO_BEGIN: usize = 0x0104;
+const LOGO_END: usize = 0x0133;
+
+// MBC1 supports up to 2 MiB of ROM and 32 KiB of RAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u8,        // 5-bit BANK1 register
+    bank2: u8,           // 2-bit RAM bank / upper ROM bank register
+    advanced_mode: bool, // banking mode select
+    multicart: bool,     // MBC1M wiring, BANK1 only drives 4 address lines
+}
+
+impl MBC1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        let multicart = is_multicart(&rom);
+        MBC1 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            bank2: 0,
+            advanced_mode: false,
+            multicart,
+        }
+    }
+
+    fn bank2_shift(&self) -> u8 {
+        if self.multicart {
+            4
+        } else {
+            5
+        }
+    }
+
+    // Bank visible at 0x0000-0x3FFF. In advanced mode BANK2 also applies here.
+    fn low_rom_bank(&self) -> usize {
+        if self.advanced_mode {
+            (self.bank2 << self.bank2_shift()) as usize
+        } else {
+            0
+        }
+    }
+
+    // Bank visible at 0x4000-0x7FFF
+    fn high_rom_bank(&self) -> usize {
+        let low = if self.multicart {
+            self.rom_bank & 0x0F
+        } else {
+            self.rom_bank
+        };
+        ((self.bank2 << self.bank2_shift()) | low) as usize
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.advanced_mode {
+            self.bank2 as usize
+        } else {
+            0
+        }
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        banked_ram_index(&self.ram, self.ram_bank(), addr as usize - 0xA000)
+    }
+}
+
+impl Mapper for MBC1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, self.low_rom_bank(), addr),
+            _ => banked_rom_byte(&self.rom, self.high_rom_bank(), addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // The zero check sees all 5 bits, so banks 0x20/0x40/0x60 can't be
+                // mapped at 0x4000
+                self.rom_bank = value & 0x1F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            }
+            0x4000..=0x5FFF => self.bank2 = value & 0b11,
+            _ => self.advanced_mode = value & 0b1 != 0,
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+// MBC1M multicarts are 1 MiB and repeat the Nintendo logo in the header of the
+// game starting at bank 0x10
+fn is_multicart(rom: &[u8]) -> bool {
+    const SECOND_HEADER: usize = 0x10 * ROM_BANK_SIZE;
+
+    rom.len() == 1024 * 1024
+        && rom[LOGO_BEGIN..=LOGO_END] == rom[SECOND_HEADER + LOGO_BEGIN..=SECOND_HEADER + LOGO_END]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn bank_zero_aliases_to_the_next_bank() {
+        let mut mbc = MBC1::new(numbered_rom(64), 0);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+
+        // Only the 5 BANK1 bits are checked, so 0x20 gives 0x21
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x21);
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(mbc.read_rom(0x4000), 0x21);
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(0x4000), 0x25);
+    }
+
+    #[test]
+    fn mode_1_banks_the_low_rom_area() {
+        let mut mbc = MBC1::new(numbered_rom(128), 0);
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x40);
+        assert_eq!(mbc.read_rom(0x4000), 0x41);
+        mbc.write_rom(0x6000, 0x00);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x41);
+    }
+
+    #[test]
+    fn bank_numbers_wrap_to_the_rom_size() {
+        // 512 KiB ignores BANK2 in either area
+        let mut mbc = MBC1::new(numbered_rom(32), 0);
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x03);
+        assert_eq!(mbc.read_rom(0x4000), 0x03);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(0x0000), 0x00);
+    }
+
+    #[test]
+    fn ram_banks_only_switch_in_mode_1() {
+        let mut mbc = MBC1::new(numbered_rom(4), 0x8000);
+        assert!(!mbc.write_ram(0xA000, 0x11));
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x02);
+        assert!(mbc.write_ram(0xA000, 0x11));
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 0x00);
+        mbc.write_ram(0xA000, 0x22);
+        mbc.write_rom(0x6000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0x11);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 0x22);
+
+        // Disabling RAM takes any value without 0xA in the low nibble
+        mbc.write_rom(0x0000, 0x1B);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    }
+}
@@ -0,0 +1,110 @@
+use super::mapper::{banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+// 512 half-byte cells of RAM built into the MBC2 chip
+const RAM_SIZE: usize = 512;
+
+// MBC2 supports up to 256 KiB of ROM and has its own 512x4-bit RAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl MBC2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        MBC2 {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for MBC2 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        // Both registers live in 0x0000-0x3FFF; address bit 8 picks which one
+        if addr >= 0x4000 {
+            return false;
+        }
+        if addr & 0x0100 == 0 {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        } else {
+            self.rom_bank = value & 0x0F;
+            if self.rom_bank == 0 {
+                self.rom_bank = 1;
+            }
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // Only the low 9 address bits are decoded, so the RAM repeats through
+        // 0xA000-0xBFFF. The upper nibble isn't connected and reads as 1s.
+        self.ram[addr as usize % RAM_SIZE] | 0xF0
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if self.ram_enabled {
+            self.ram[addr as usize % RAM_SIZE] = value & 0x0F;
+        }
+        self.ram_enabled
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn address_bit_8_selects_the_register() {
+        let mut mbc = MBC2::new(numbered_rom(16));
+        mbc.write_rom(0x2100, 0x05);
+        assert_eq!(mbc.read_rom(0x4000), 0x05);
+        mbc.write_rom(0x2100, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+
+        // Bit 8 clear is the RAM enable, anywhere in 0x0000-0x3FFF
+        mbc.write_rom(0x3E00, 0x0A);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+        assert!(mbc.write_ram(0xA000, 0xAB));
+        mbc.write_rom(0x0000, 0x00);
+        assert!(!mbc.write_ram(0xA000, 0xCD));
+    }
+
+    #[test]
+    fn ram_is_4_bits_wide_and_repeats() {
+        let mut mbc = MBC2::new(numbered_rom(2));
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(0xA001, 0xAB);
+        assert_eq!(mbc.read_ram(0xA001) & 0x0F, 0x0B);
+        assert_eq!(mbc.read_ram(0xA201) & 0x0F, 0x0B);
+        assert_eq!(mbc.read_ram(0xBE01) & 0x0F, 0x0B);
+    }
+}
@@ -0,0 +1,181 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+use super::rtc::{Rtc, RtcClock};
+
+// What 0xA000-0xBFFF is connected to
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RamSelect {
+    RamBank(u8),
+    RtcRegister(u8), // 0x08-0x0C
+    None,
+}
+
+// MBC3 supports up to 2 MiB of ROM, 32 KiB of RAM and an optional real-time clock
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool, // also gates access to the clock registers
+    rom_bank: u8,
+    ram_select: RamSelect,
+    rtc: Option<Rtc>,
+}
+
+impl MBC3 {
+    // `rtc` is the clock source for cartridges with a timer, `None` otherwise
+    pub fn new(rom: Vec<u8>, ram_size: usize, rtc: Option<RtcClock>) -> Self {
+        MBC3 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: RamSelect::RamBank(0),
+            rtc: rtc.map(Rtc::new),
+        }
+    }
+
+    fn ram_index(&self, addr: u16, bank: u8) -> Option<usize> {
+        banked_ram_index(&self.ram, bank as usize, addr as usize - 0xA000)
+    }
+}
+
+impl Mapper for MBC3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                self.rom_bank = value & 0x7F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            }
+            0x4000..=0x5FFF => {
+                self.ram_select = match value {
+                    0x00..=0x07 => RamSelect::RamBank(value),
+                    0x08..=0x0C if self.rtc.is_some() => RamSelect::RtcRegister(value),
+                    _ => RamSelect::None,
+                }
+            }
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    // The latched registers are part of the save
+                    rtc.write_latch(value);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match self.ram_select {
+            RamSelect::RamBank(bank) => match self.ram_index(addr, bank) {
+                Some(idx) => self.ram[idx],
+                None => 0xFF,
+            },
+            RamSelect::RtcRegister(register) => match &self.rtc {
+                Some(rtc) => rtc.read(register),
+                None => 0xFF,
+            },
+            RamSelect::None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+        match self.ram_select {
+            RamSelect::RamBank(bank) => {
+                if let Some(idx) = self.ram_index(addr, bank) {
+                    self.ram[idx] = value;
+                    return true;
+                }
+            }
+            RamSelect::RtcRegister(register) => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write(register, value);
+                    return true;
+                }
+            }
+            RamSelect::None => {}
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn step(&mut self, cycles: u32) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.step(cycles);
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend(rtc.to_footer());
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        if let Some(rtc) = &mut self.rtc {
+            let footer = data.get(self.ram.len()..).unwrap_or(&[]);
+            rtc.load_footer(footer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn rom_bank_has_7_bits_and_skips_zero() {
+        let mut mbc = MBC3::new(numbered_rom(128), 0, None);
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(0x4000), 0x7F);
+        mbc.write_rom(0x2000, 0x80);
+        assert_eq!(mbc.read_rom(0x4000), 0x01);
+    }
+
+    #[test]
+    fn ram_bank_or_clock_register_is_selected() {
+        let mut mbc = MBC3::new(numbered_rom(2), 0x8000, Some(RtcClock::Cycles));
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x03);
+        mbc.write_ram(0xA000, 0x33);
+        mbc.write_rom(0x4000, 0x00);
+        assert_eq!(mbc.read_ram(0xA000), 0x00);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_ram(0xA000), 0x33);
+
+        // Minutes register, read back through the latch
+        mbc.write_rom(0x4000, 0x09);
+        mbc.write_ram(0xA000, 42);
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(0xA000), 42);
+
+        // Without a clock its registers are open bus
+        let mut mbc = MBC3::new(numbered_rom(2), 0x8000, None);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x09);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    }
+}
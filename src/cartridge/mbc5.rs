@@ -0,0 +1,129 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+// MBC5 supports up to 8 MiB of ROM and 128 KiB of RAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u16, // 9 bits
+    ram_bank: u8,
+    has_rumble: bool,
+    rumble: bool, // motor state
+}
+
+impl MBC5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Self {
+        MBC5 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        banked_ram_index(&self.ram, self.ram_bank as usize, addr as usize - 0xA000)
+    }
+}
+
+impl Mapper for MBC5 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            // Unlike earlier MBCs, bank 0 can be mapped here too
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0b1) << 8),
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    // Bit 3 drives the rumble motor instead of the RAM bank
+                    self.rumble = value & 0x08 != 0;
+                    self.ram_bank = value & 0x07;
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn rom_bank_has_9_bits_and_maps_zero() {
+        let mut mbc = MBC5::new(numbered_rom(512), 0, false);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(0x4000), 0x00);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!((mbc.read_rom(0x4000), mbc.read_rom(0x4001)), (0x00, 0x01));
+        mbc.write_rom(0x2000, 0xFF);
+        assert_eq!((mbc.read_rom(0x4000), mbc.read_rom(0x4001)), (0xFF, 0x01));
+    }
+
+    #[test]
+    fn rumble_carts_take_bit_3_for_the_motor() {
+        let mut mbc = MBC5::new(numbered_rom(2), 0x20000, false);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0F);
+        mbc.write_ram(0xA000, 0x0F);
+        assert!(!mbc.rumble());
+
+        let mut mbc = MBC5::new(numbered_rom(2), 0x20000, true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x07);
+        mbc.write_ram(0xA000, 0x07);
+        mbc.write_rom(0x4000, 0x0F);
+        assert!(mbc.rumble());
+        assert_eq!(mbc.read_ram(0xA000), 0x07);
+    }
+}
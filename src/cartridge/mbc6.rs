@@ -0,0 +1,199 @@
+use super::mapper::{banked_rom_byte, load_ram, Mapper};
+
+// MBC6 maps 8 KiB ROM/flash banks and 4 KiB RAM banks
+const BANK_SIZE: usize = 0x2000;
+const RAM_BANK_SIZE: usize = 0x1000;
+const FLASH_SIZE: usize = 1024 * 1024;
+
+// Progress through the flash chip's unlock/command sequences
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FlashState {
+    Ready,
+    Unlocked1, // 0xAA written to 0x5555
+    Unlocked2, // 0x55 written to 0x2AAA, waiting for a command
+    Program,   // next write programs a byte
+    Erase,     // 0x80 received, waiting for a second unlock
+    EraseUnlocked1,
+    EraseUnlocked2,
+}
+
+// One of the two switchable 8 KiB windows at 0x4000 and 0x6000
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Window {
+    bank: u8,
+    flash: bool, // maps flash instead of ROM
+}
+
+// MBC6, used by Net de Get: two independently banked ROM windows that can map
+// 1 MiB of flash, and two independently banked RAM windows
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC6 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    flash: Vec<u8>,
+    ram_enabled: bool,
+    ram_banks: [u8; 2], // 0xA000-0xAFFF and 0xB000-0xBFFF
+    windows: [Window; 2],
+    flash_enabled: bool,
+    flash_write_enabled: bool,
+    flash_state: FlashState,
+    flash_written: bool, // programmed or loaded, so it goes into the save
+}
+
+impl MBC6 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        MBC6 {
+            rom,
+            ram: vec![0; ram_size],
+            flash: vec![0xFF; FLASH_SIZE],
+            ram_enabled: false,
+            ram_banks: [0; 2],
+            windows: [Window {
+                bank: 0,
+                flash: false,
+            }; 2],
+            flash_enabled: false,
+            flash_write_enabled: false,
+            flash_state: FlashState::Ready,
+            flash_written: false,
+        }
+    }
+
+    fn window(addr: u16) -> usize {
+        (addr as usize - 0x4000) / BANK_SIZE
+    }
+
+    fn flash_index(bank: u8, addr: u16) -> usize {
+        (bank as usize * BANK_SIZE + (addr as usize & (BANK_SIZE - 1))) % FLASH_SIZE
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+        let window = (addr as usize - 0xA000) / RAM_BANK_SIZE;
+        let offset = addr as usize & (RAM_BANK_SIZE - 1);
+        Some((self.ram_banks[window] as usize * RAM_BANK_SIZE + offset) % self.ram.len())
+    }
+
+    // Commands are recognised by the address within the flash chip, which is
+    // the window's bank plus the offset into the window. Returns whether the
+    // flash contents changed.
+    fn write_flash(&mut self, bank: u8, addr: u16, value: u8) -> bool {
+        let index = MBC6::flash_index(bank, addr);
+        let command_addr = index & 0x7FFF;
+        let mut written = false;
+        self.flash_state = match (self.flash_state, command_addr, value) {
+            (_, _, 0xF0) => FlashState::Ready,
+            (FlashState::Ready, 0x5555, 0xAA) => FlashState::Unlocked1,
+            (FlashState::Unlocked1, 0x2AAA, 0x55) => FlashState::Unlocked2,
+            (FlashState::Unlocked2, 0x5555, 0xA0) => FlashState::Program,
+            (FlashState::Unlocked2, 0x5555, 0x80) => FlashState::Erase,
+            (FlashState::Program, _, _) => {
+                // Programming can only clear bits
+                if self.flash_write_enabled {
+                    self.flash[index] &= value;
+                    written = true;
+                }
+                FlashState::Ready
+            }
+            (FlashState::Erase, 0x5555, 0xAA) => FlashState::EraseUnlocked1,
+            (FlashState::EraseUnlocked1, 0x2AAA, 0x55) => FlashState::EraseUnlocked2,
+            (FlashState::EraseUnlocked2, _, 0x30) => {
+                if self.flash_write_enabled {
+                    let begin = index & !(BANK_SIZE - 1);
+                    self.flash[begin..begin + BANK_SIZE].fill(0xFF);
+                    written = true;
+                }
+                FlashState::Ready
+            }
+            (FlashState::EraseUnlocked2, 0x5555, 0x10) => {
+                if self.flash_write_enabled {
+                    self.flash.fill(0xFF);
+                    written = true;
+                }
+                FlashState::Ready
+            }
+            _ => FlashState::Ready,
+        };
+        self.flash_written |= written;
+        written
+    }
+}
+
+impl Mapper for MBC6 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            return banked_rom_byte(&self.rom, 0, addr as usize);
+        }
+        let window = self.windows[MBC6::window(addr)];
+        if window.flash {
+            if !self.flash_enabled {
+                return 0xFF;
+            }
+            return self.flash[MBC6::flash_index(window.bank, addr)];
+        }
+        // banked_rom_byte works in 16 KiB banks, so split the 8 KiB bank number
+        let bank = window.bank as usize;
+        let offset = (bank & 0b1) * BANK_SIZE + (addr as usize & (BANK_SIZE - 1));
+        banked_rom_byte(&self.rom, bank >> 1, offset)
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x03FF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0400..=0x07FF => self.ram_banks[0] = value & 0x07,
+            0x0800..=0x0BFF => self.ram_banks[1] = value & 0x07,
+            0x0C00..=0x0FFF => self.flash_enabled = value & 0b1 != 0,
+            0x1000 => self.flash_write_enabled = value & 0b1 != 0,
+            0x2000..=0x27FF => self.windows[0].bank = value & 0x7F,
+            0x2800..=0x2FFF => self.windows[0].flash = value == 0x08,
+            0x3000..=0x37FF => self.windows[1].bank = value & 0x7F,
+            0x3800..=0x3FFF => self.windows[1].flash = value == 0x08,
+            0x4000..=0x7FFF => {
+                let window = self.windows[MBC6::window(addr)];
+                if window.flash && self.flash_enabled {
+                    return self.write_flash(window.bank, addr, value);
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    // RAM followed by the full flash image, once anything was written to it
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if self.flash_written {
+            data.extend_from_slice(&self.flash);
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+        if let Some(flash) = data.get(self.ram.len()..).filter(|flash| !flash.is_empty()) {
+            load_ram(&mut self.flash, flash);
+            self.flash_written = true;
+        }
+    }
+}
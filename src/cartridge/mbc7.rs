@@ -0,0 +1,420 @@
+use super::mapper::{banked_rom_byte, Mapper, ROM_BANK_SIZE};
+
+// Accelerometer reading when level, and the change per g of tilt
+const ACCEL_CENTER: u16 = 0x81D0;
+const ACCEL_PER_G: f32 = 112.0;
+// Reading after the latch is cleared, until a new value is latched
+const ACCEL_ERASED: u16 = 0x8000;
+
+// 93LC56 EEPROM organised as 128 16-bit words
+const EEPROM_WORDS: usize = 128;
+
+// Port bits at 0xAx8x
+const EEPROM_DO: u8 = 0b0000_0001;
+const EEPROM_DI: u8 = 0b0000_0010;
+const EEPROM_CLK: u8 = 0b0100_0000;
+const EEPROM_CS: u8 = 0b1000_0000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum EepromState {
+    Idle, // waiting for a start bit
+    // Shifting in 2 opcode bits and 8 address bits
+    Command {
+        bits: u16,
+        count: u8,
+    },
+    // Shifting out words, starting at `address`
+    Read {
+        address: u8,
+        data: u16,
+        count: u8,
+    },
+    // Shifting in a data word, for one address or for all of them
+    Write {
+        address: Option<u8>,
+        data: u16,
+        count: u8,
+    },
+}
+
+// Serial EEPROM bit-banged through the 0xAx8x port
+#[derive(Clone, Debug, PartialEq)]
+struct Eeprom {
+    words: [u16; EEPROM_WORDS],
+    state: EepromState,
+    write_enabled: bool,
+    cs: bool,
+    clk: bool,
+    di: bool,
+    data_out: bool,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Eeprom {
+            words: [0xFFFF; EEPROM_WORDS],
+            state: EepromState::Idle,
+            write_enabled: false,
+            cs: false,
+            clk: false,
+            di: false,
+            data_out: true,
+        }
+    }
+
+    fn read(&self) -> u8 {
+        let mut value = 0;
+        if self.cs {
+            value |= EEPROM_CS;
+        }
+        if self.clk {
+            value |= EEPROM_CLK;
+        }
+        if self.di {
+            value |= EEPROM_DI;
+        }
+        if self.data_out {
+            value |= EEPROM_DO;
+        }
+        value
+    }
+
+    fn write(&mut self, value: u8) {
+        let cs = value & EEPROM_CS != 0;
+        let clk = value & EEPROM_CLK != 0;
+        self.di = value & EEPROM_DI != 0;
+        if !cs {
+            // Dropping chip select aborts any command and reports ready
+            self.state = EepromState::Idle;
+            self.data_out = true;
+        } else if clk && !self.clk {
+            self.clock_in(self.di);
+        }
+        self.cs = cs;
+        self.clk = clk;
+    }
+
+    // Bits are sampled on the rising edge of CLK
+    fn clock_in(&mut self, bit: bool) {
+        self.state = match self.state {
+            EepromState::Idle if bit => EepromState::Command { bits: 0, count: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits, count } => {
+                let bits = bits << 1 | bit as u16;
+                if count + 1 == 10 {
+                    self.execute(bits)
+                } else {
+                    EepromState::Command {
+                        bits,
+                        count: count + 1,
+                    }
+                }
+            }
+            EepromState::Read {
+                address,
+                data,
+                count,
+            } => {
+                self.data_out = data & 0x8000 != 0;
+                if count + 1 == 16 {
+                    // Reads continue into the following word
+                    let address = (address + 1) % EEPROM_WORDS as u8;
+                    EepromState::Read {
+                        address,
+                        data: self.words[address as usize],
+                        count: 0,
+                    }
+                } else {
+                    EepromState::Read {
+                        address,
+                        data: data << 1,
+                        count: count + 1,
+                    }
+                }
+            }
+            EepromState::Write {
+                address,
+                data,
+                count,
+            } => {
+                let data = data << 1 | bit as u16;
+                if count + 1 == 16 {
+                    if self.write_enabled {
+                        match address {
+                            Some(address) => self.words[address as usize] = data,
+                            None => self.words.fill(data),
+                        }
+                    }
+                    self.data_out = true;
+                    EepromState::Idle
+                } else {
+                    EepromState::Write {
+                        address,
+                        data,
+                        count: count + 1,
+                    }
+                }
+            }
+        };
+    }
+
+    // `bits` holds the 2-bit opcode followed by 8 address bits
+    fn execute(&mut self, bits: u16) -> EepromState {
+        let address = (bits & 0x7F) as u8;
+        match bits >> 8 {
+            // READ, a dummy 0 bit precedes the data
+            0b10 => {
+                self.data_out = false;
+                EepromState::Read {
+                    address,
+                    data: self.words[address as usize],
+                    count: 0,
+                }
+            }
+            // WRITE
+            0b01 => EepromState::Write {
+                address: Some(address),
+                data: 0,
+                count: 0,
+            },
+            // ERASE
+            0b11 => {
+                if self.write_enabled {
+                    self.words[address as usize] = 0xFFFF;
+                }
+                EepromState::Idle
+            }
+            // Extended opcodes use the top 2 address bits
+            _ => match (bits >> 6) & 0b11 {
+                // EWEN
+                0b11 => {
+                    self.write_enabled = true;
+                    EepromState::Idle
+                }
+                // EWDS
+                0b00 => {
+                    self.write_enabled = false;
+                    EepromState::Idle
+                }
+                // ERAL
+                0b10 => {
+                    if self.write_enabled {
+                        self.words.fill(0xFFFF);
+                    }
+                    EepromState::Idle
+                }
+                // WRAL
+                _ => EepromState::Write {
+                    address: None,
+                    data: 0,
+                    count: 0,
+                },
+            },
+        }
+    }
+}
+
+// MBC7, used by Kirby Tilt 'n' Tumble: a 2-axis accelerometer and a serial
+// EEPROM in place of RAM
+#[derive(Clone, Debug, PartialEq)]
+pub struct MBC7 {
+    rom: Vec<u8>,
+    rom_bank: u8,
+    ram_enabled: [bool; 2], // both enables must be set to access 0xA000-0xAFFF
+    tilt: (u16, u16),       // live sensor reading from the host
+    latched: (u16, u16),
+    latch_armed: bool, // 0x55 written, a 0xAA write latches
+    eeprom: Eeprom,
+}
+
+impl MBC7 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        MBC7 {
+            rom,
+            rom_bank: 1,
+            ram_enabled: [false; 2],
+            tilt: (ACCEL_CENTER, ACCEL_CENTER),
+            latched: (ACCEL_ERASED, ACCEL_ERASED),
+            latch_armed: false,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    fn registers_enabled(&self, addr: u16) -> bool {
+        self.ram_enabled[0] && self.ram_enabled[1] && addr < 0xB000
+    }
+}
+
+impl Mapper for MBC7 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, 0, addr),
+            _ => banked_rom_byte(&self.rom, self.rom_bank as usize, addr - ROM_BANK_SIZE),
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled[0] = value == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = value & 0x7F,
+            0x4000..=0x5FFF => self.ram_enabled[1] = value == 0x40,
+            _ => {}
+        }
+        false
+    }
+
+    // Registers are selected by bits 4-7 and repeat every 256 bytes
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.registers_enabled(addr) {
+            return 0xFF;
+        }
+        match (addr >> 4) & 0x0F {
+            0x2 => self.latched.0 as u8,
+            0x3 => (self.latched.0 >> 8) as u8,
+            0x4 => self.latched.1 as u8,
+            0x5 => (self.latched.1 >> 8) as u8,
+            0x6 => 0x00,
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if !self.registers_enabled(addr) {
+            return false;
+        }
+        match (addr >> 4) & 0x0F {
+            0x0 if value == 0x55 => {
+                self.latched = (ACCEL_ERASED, ACCEL_ERASED);
+                self.latch_armed = true;
+            }
+            0x1 if value == 0xAA && self.latch_armed => {
+                self.latched = self.tilt;
+                self.latch_armed = false;
+            }
+            // Most port writes only clock bits in, few finish a write
+            0x8 => {
+                let words = self.eeprom.words;
+                self.eeprom.write(value);
+                return self.eeprom.words != words;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        let axis = |g: f32| (ACCEL_CENTER as f32 + g * ACCEL_PER_G).clamp(0.0, 65535.0) as u16;
+        self.tilt = (axis(x), axis(y));
+    }
+
+    // The EEPROM as 256 bytes, each word little endian
+    fn save_data(&self) -> Vec<u8> {
+        self.eeprom
+            .words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        for (word, bytes) in self.eeprom.words.iter_mut().zip(data.chunks_exact(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    fn enabled_mbc7() -> MBC7 {
+        let mut mbc = MBC7::new(numbered_rom(4));
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x40);
+        mbc
+    }
+
+    // Clocks the low `count` bits of `bits` into the EEPROM, MSB first,
+    // returning whether any of them changed its contents
+    fn clock_bits(mbc: &mut MBC7, bits: u32, count: u8) -> bool {
+        let mut written = false;
+        for bit in (0..count).rev() {
+            let di = if bits >> bit & 0b1 != 0 { EEPROM_DI } else { 0 };
+            mbc.write_ram(0xA080, EEPROM_CS | di);
+            written |= mbc.write_ram(0xA080, EEPROM_CS | EEPROM_CLK | di);
+        }
+        written
+    }
+
+    // Sends a start bit, 2 opcode bits, 8 address bits and any data, then
+    // drops chip select
+    fn command(mbc: &mut MBC7, bits: u32, count: u8) -> bool {
+        let written = clock_bits(mbc, bits, count);
+        mbc.write_ram(0xA080, 0x00);
+        written
+    }
+
+    // READ `address`, then clocks 16 data bits out of DO
+    fn read_word(mbc: &mut MBC7, address: u8) -> u16 {
+        clock_bits(mbc, 0b110 << 8 | address as u32, 11);
+        // The dummy bit
+        assert_eq!(mbc.read_ram(0xA080) & EEPROM_DO, 0);
+        let mut word = 0;
+        for _ in 0..16 {
+            clock_bits(mbc, 0, 1);
+            word = word << 1 | (mbc.read_ram(0xA080) & EEPROM_DO) as u16;
+        }
+        mbc.write_ram(0xA080, 0x00);
+        word
+    }
+
+    #[test]
+    fn eeprom_writes_need_ewen_and_read_back() {
+        let mut mbc = enabled_mbc7();
+        // WRITE 0x03 = 0x1234 while write protected
+        assert!(!command(&mut mbc, (0b101 << 8 | 0x03) << 16 | 0x1234, 27));
+        assert_eq!(read_word(&mut mbc, 0x03), 0xFFFF);
+
+        // EWEN
+        assert!(!command(&mut mbc, 0b100 << 8 | 0b1100_0000, 11));
+        assert!(command(&mut mbc, (0b101 << 8 | 0x03) << 16 | 0x1234, 27));
+        assert_eq!(read_word(&mut mbc, 0x03), 0x1234);
+        assert_eq!(read_word(&mut mbc, 0x04), 0xFFFF);
+        assert_eq!(mbc.save_data()[6..8], [0x34, 0x12]);
+    }
+
+    #[test]
+    fn accelerometer_latches_on_0x55_then_0xaa() {
+        let mut mbc = enabled_mbc7();
+        let latched = |mbc: &MBC7| {
+            (0x20..=0x50)
+                .step_by(0x10)
+                .map(|addr| mbc.read_ram(0xA000 | addr))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(latched(&mbc), [0x00, 0x80, 0x00, 0x80]);
+
+        // 0xAA alone doesn't latch
+        mbc.write_ram(0xA010, 0xAA);
+        assert_eq!(latched(&mbc), [0x00, 0x80, 0x00, 0x80]);
+
+        mbc.write_ram(0xA000, 0x55);
+        mbc.write_ram(0xA010, 0xAA);
+        assert_eq!(latched(&mbc), [0xD0, 0x81, 0xD0, 0x81]);
+
+        // New readings wait for the next latch, and 0x55 erases the old one
+        mbc.set_tilt(1.0, -1.0);
+        assert_eq!(latched(&mbc), [0xD0, 0x81, 0xD0, 0x81]);
+        mbc.write_ram(0xA000, 0x55);
+        assert_eq!(latched(&mbc), [0x00, 0x80, 0x00, 0x80]);
+        mbc.write_ram(0xA010, 0xAA);
+        assert_eq!(latched(&mbc), [0x40, 0x82, 0x60, 0x81]);
+    }
+}
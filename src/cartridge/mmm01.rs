@@ -0,0 +1,182 @@
+use super::mapper::{banked_ram_index, banked_rom_byte, load_ram, Mapper, ROM_BANK_SIZE};
+
+// MMM01 multicart: boots into a menu in the last 32 KiB of ROM, which picks a
+// game by writing its outer ROM/RAM bank and bank mask and then locking the
+// mapping. From then on the game sees an MBC1-like mapper confined to its
+// slice of ROM and RAM.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MMM01 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    locked: bool, // the menu has handed over to a game
+    ram_enabled: bool,
+    rom_bank: u8,      // 5-bit bank within the game
+    rom_bank_high: u8, // bits 5-8 of the bank, the game's outer bank
+    rom_mask: u8,      // bits 1-4 of the bank fixed by the outer bank
+    ram_bank: u8,      // 2-bit bank within the game
+    ram_bank_high: u8, // bits 2-3 of the RAM bank
+}
+
+impl MMM01 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        MMM01 {
+            rom,
+            ram: vec![0; ram_size],
+            locked: false,
+            ram_enabled: false,
+            rom_bank: 0,
+            rom_bank_high: 0,
+            rom_mask: 0,
+            ram_bank: 0,
+            ram_bank_high: 0,
+        }
+    }
+
+    fn rom_banks(&self) -> usize {
+        (self.rom.len() / ROM_BANK_SIZE).max(2)
+    }
+
+    // Bits of the bank number selected by the menu rather than the game
+    fn outer_rom_bank(&self) -> usize {
+        (self.rom_bank_high as usize) << 5 | (self.rom_bank & self.rom_mask) as usize
+    }
+
+    fn low_rom_bank(&self) -> usize {
+        if self.locked {
+            self.outer_rom_bank()
+        } else {
+            self.rom_banks() - 2
+        }
+    }
+
+    fn high_rom_bank(&self) -> usize {
+        if !self.locked {
+            return self.rom_banks() - 1;
+        }
+        let mut bank = self.rom_bank & !self.rom_mask & 0x1F;
+        if bank == 0 {
+            bank = 1;
+        }
+        self.outer_rom_bank() | bank as usize
+    }
+
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+        let bank = (self.ram_bank_high << 2 | self.ram_bank) as usize;
+        banked_ram_index(&self.ram, bank, addr as usize - 0xA000)
+    }
+}
+
+impl Mapper for MMM01 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        let addr = addr as usize;
+        match addr {
+            0x0000..=0x3FFF => banked_rom_byte(&self.rom, self.low_rom_bank(), addr),
+            _ => banked_rom_byte(&self.rom, self.high_rom_bank(), addr - ROM_BANK_SIZE),
+        }
+    }
+
+    // Until locked, each register also carries the menu-only outer bank bits
+    fn write_rom(&mut self, addr: u16, value: u8) -> bool {
+        match addr {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+                if value & 0x40 != 0 {
+                    self.locked = true;
+                }
+            }
+            0x2000..=0x3FFF => {
+                if self.locked {
+                    // Masked bits stay as the menu left them
+                    self.rom_bank =
+                        (self.rom_bank & self.rom_mask) | (value & 0x1F & !self.rom_mask);
+                } else {
+                    self.rom_bank = value & 0x1F;
+                    self.rom_bank_high = (self.rom_bank_high & 0b1100) | (value >> 5) & 0b11;
+                }
+            }
+            0x4000..=0x5FFF => {
+                self.ram_bank = value & 0b11;
+                if !self.locked {
+                    self.ram_bank_high = (value >> 2) & 0b11;
+                    self.rom_bank_high = (self.rom_bank_high & 0b0011) | (value >> 2) & 0b1100;
+                }
+            }
+            _ => {
+                if !self.locked {
+                    self.rom_mask = (value & 0b0011_1100) >> 1;
+                }
+            }
+        }
+        false
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_index(addr) {
+            Some(idx) => self.ram[idx],
+            None => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, value: u8) -> bool {
+        if let Some(idx) = self.ram_index(addr) {
+            self.ram[idx] = value;
+            return true;
+        }
+        false
+    }
+
+    fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mapper::numbered_rom;
+    use super::*;
+
+    #[test]
+    fn menu_maps_the_last_32_kib_until_locked() {
+        let mut mmm01 = MMM01::new(numbered_rom(64), 0);
+        assert_eq!(mmm01.read_rom(0x0000), 62);
+        assert_eq!(mmm01.read_rom(0x4000), 63);
+        // Bank writes don't move the menu
+        mmm01.write_rom(0x2000, 0x05);
+        assert_eq!(mmm01.read_rom(0x4000), 63);
+    }
+
+    #[test]
+    fn locked_game_banks_within_its_outer_bank() {
+        let mut mmm01 = MMM01::new(numbered_rom(64), 0);
+        // Outer bank 32 plus bank bit 4 fixed by the mask: banks 48-63
+        mmm01.write_rom(0x2000, 0x30);
+        mmm01.write_rom(0x6000, 0x20);
+        mmm01.write_rom(0x0000, 0x40);
+        assert_eq!(mmm01.read_rom(0x0000), 48);
+        assert_eq!(mmm01.read_rom(0x4000), 49);
+
+        mmm01.write_rom(0x2000, 0x0F);
+        assert_eq!(mmm01.read_rom(0x4000), 63);
+        // Masked bits can't be cleared by the game
+        mmm01.write_rom(0x2000, 0x00);
+        assert_eq!(mmm01.read_rom(0x4000), 49);
+
+        // Neither can the outer bank or mask change once locked
+        mmm01.write_rom(0x4000, 0x3C);
+        mmm01.write_rom(0x6000, 0x00);
+        mmm01.write_rom(0x2000, 0x02);
+        assert_eq!(mmm01.read_rom(0x0000), 48);
+        assert_eq!(mmm01.read_rom(0x4000), 50);
+    }
+}
@@ -0,0 +1,432 @@
+pub mod camera;
+pub mod header;
+pub mod huc1;
+pub mod huc3;
+pub mod mapper;
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod mbc6;
+pub mod mbc7;
+pub mod mmm01;
+pub mod rtc;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use self::camera::PocketCamera;
+use self::header::{global_checksum, CartridgeHeader, MapperType};
+use self::huc1::HuC1;
+use self::huc3::HuC3;
+use self::mapper::{Mapper, RomOnly};
+use self::mbc1::MBC1;
+use self::mbc2::MBC2;
+use self::mbc3::MBC3;
+use self::mbc5::MBC5;
+use self::mbc6::MBC6;
+use self::mbc7::MBC7;
+use self::mmm01::MMM01;
+use self::rtc::RtcClock;
+
+// MMM01 dumps usually start with the first game, with the menu and the
+// multicart's own header in the last 32 KiB
+const MMM01_MENU_SIZE: usize = 0x8000;
+
+// How often dirty battery RAM is written back to disk, in machine cycles (~1s)
+const SAVE_INTERVAL_CYCLES: u32 = 1_048_576;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    TooSmall(usize), // file ends before the header does
+    InvalidRomSize(u8),
+    InvalidRamSize(u8),
+    UnsupportedCartridgeType(u8),
+    UnsupportedMapper(MapperType),
+    RomSizeMismatch { expected: usize, actual: usize },
+    HeaderChecksumMismatch { expected: u8, actual: u8 },
+    GlobalChecksumMismatch { expected: u16, actual: u16 },
+    NoBattery, // cartridge has nothing to save
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(err) => write!(f, "failed to read ROM: {}", err),
+            CartridgeError::TooSmall(len) => {
+                write!(f, "ROM is {} bytes, too small to contain a header", len)
+            }
+            CartridgeError::InvalidRomSize(code) => {
+                write!(f, "invalid ROM size code {:#04X}", code)
+            }
+            CartridgeError::InvalidRamSize(code) => {
+                write!(f, "invalid RAM size code {:#04X}", code)
+            }
+            CartridgeError::UnsupportedCartridgeType(code) => {
+                write!(f, "unsupported cartridge type {:#04X}", code)
+            }
+            CartridgeError::UnsupportedMapper(mapper) => {
+                write!(f, "{:?} cartridges are not supported yet", mapper)
+            }
+            CartridgeError::RomSizeMismatch { expected, actual } => write!(
+                f,
+                "header declares {} bytes of ROM but the file has {}",
+                expected, actual
+            ),
+            CartridgeError::HeaderChecksumMismatch { expected, actual } => write!(
+                f,
+                "header checksum mismatch: expected {:#04X}, computed {:#04X}",
+                expected, actual
+            ),
+            CartridgeError::GlobalChecksumMismatch { expected, actual } => write!(
+                f,
+                "global checksum mismatch: expected {:#06X}, computed {:#06X}",
+                expected, actual
+            ),
+            CartridgeError::NoBattery => write!(f, "cartridge has no battery-backed memory"),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CartridgeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(err: io::Error) -> Self {
+        CartridgeError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct Cartridge {
+    pub header: CartridgeHeader,
+    mapper: Box<dyn Mapper>,
+    save_path: Option<PathBuf>, // .sav file for battery-backed RAM
+    save_dirty: bool,
+    cycles_since_save: u32,
+    last_save_error: Option<CartridgeError>, // from the last periodic write
+    global_checksum_error: Option<CartridgeError>,
+}
+
+impl Cartridge {
+    // Loads a .gb/.gbc file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, CartridgeError> {
+        Cartridge::from_file_with_clock(path, RtcClock::WallClock)
+    }
+
+    // Like `from_file`, choosing how a cartridge real-time clock measures time
+    pub fn from_file_with_clock<P: AsRef<Path>>(
+        path: P,
+        rtc_clock: RtcClock,
+    ) -> Result<Self, CartridgeError> {
+        let path = path.as_ref();
+        let rom = fs::read(path)?;
+        let mut cartridge = Cartridge::from_bytes_with_clock(rom, rtc_clock)?;
+
+        if cartridge.has_battery() {
+            let save_path = path.with_extension("sav");
+            match fs::read(&save_path) {
+                Ok(data) => cartridge.mapper.load_save_data(&data),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+            cartridge.save_path = Some(save_path);
+        }
+        Ok(cartridge)
+    }
+
+    pub fn from_bytes(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        Cartridge::from_bytes_with_clock(rom, RtcClock::WallClock)
+    }
+
+    pub fn from_bytes_with_clock(
+        rom: Vec<u8>,
+        rtc_clock: RtcClock,
+    ) -> Result<Self, CartridgeError> {
+        let header = match mmm01_header(&rom) {
+            Some(header) => header,
+            None => CartridgeHeader::parse(&rom)?,
+        };
+        if rom.len() < header.rom_size {
+            return Err(CartridgeError::RomSizeMismatch {
+                expected: header.rom_size,
+                actual: rom.len(),
+            });
+        }
+
+        // Hardware doesn't check it either, so a mismatch is only reported
+        let actual = global_checksum(&rom);
+        let global_checksum_error =
+            (actual != header.global_checksum).then_some(CartridgeError::GlobalChecksumMismatch {
+                expected: header.global_checksum,
+                actual,
+            });
+
+        let ram_size = header.ram_size;
+        let cartridge_type = header.cartridge_type;
+        let mapper: Box<dyn Mapper> = match cartridge_type.mapper {
+            MapperType::RomOnly => Box::new(RomOnly::new(rom, ram_size)),
+            MapperType::MBC1 => Box::new(MBC1::new(rom, ram_size)),
+            MapperType::MBC2 => Box::new(MBC2::new(rom)),
+            MapperType::MBC3 => {
+                let rtc = cartridge_type.timer.then_some(rtc_clock);
+                Box::new(MBC3::new(rom, ram_size, rtc))
+            }
+            MapperType::MBC5 => Box::new(MBC5::new(rom, ram_size, cartridge_type.rumble)),
+            MapperType::MBC6 => Box::new(MBC6::new(rom, ram_size)),
+            MapperType::MBC7 => Box::new(MBC7::new(rom)),
+            MapperType::MMM01 => Box::new(MMM01::new(rom, ram_size)),
+            MapperType::PocketCamera => Box::new(PocketCamera::new(rom, ram_size)),
+            MapperType::HuC1 => Box::new(HuC1::new(rom, ram_size)),
+            MapperType::HuC3 => Box::new(HuC3::new(rom, ram_size, rtc_clock)),
+            mapper => return Err(CartridgeError::UnsupportedMapper(mapper)),
+        };
+        Ok(Cartridge {
+            header,
+            mapper,
+            save_path: None,
+            save_dirty: false,
+            cycles_since_save: 0,
+            last_save_error: None,
+            global_checksum_error,
+        })
+    }
+
+    // Mismatch found on load, which doesn't stop the cartridge from running
+    pub fn global_checksum_error(&self) -> Option<&CartridgeError> {
+        self.global_checksum_error.as_ref()
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        self.mapper.rom()
+    }
+
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        self.mapper.read_rom(addr)
+    }
+
+    pub fn write_rom(&mut self, addr: u16, value: u8) {
+        if self.mapper.write_rom(addr, value) && self.has_battery() {
+            self.save_dirty = true;
+        }
+    }
+
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        self.mapper.read_ram(addr)
+    }
+
+    pub fn write_ram(&mut self, addr: u16, value: u8) {
+        if self.mapper.write_ram(addr, value) && self.has_battery() {
+            self.save_dirty = true;
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        self.mapper.step(cycles);
+
+        self.cycles_since_save += cycles;
+        if self.cycles_since_save >= SAVE_INTERVAL_CYCLES {
+            self.cycles_since_save = 0;
+            self.last_save_error = self.flush_save().err();
+        }
+    }
+
+    // Why the last periodic write of the .sav file failed, if it did
+    pub fn last_save_error(&self) -> Option<&CartridgeError> {
+        self.last_save_error.as_ref()
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header.cartridge_type.battery
+    }
+
+    // Raw save bytes: battery RAM plus any real-time clock footer
+    pub fn export_save(&self) -> Result<Vec<u8>, CartridgeError> {
+        if !self.has_battery() {
+            return Err(CartridgeError::NoBattery);
+        }
+        Ok(self.mapper.save_data())
+    }
+
+    // Replaces battery RAM (and clock state, if a footer is present) with `data`
+    pub fn import_save(&mut self, data: &[u8]) -> Result<(), CartridgeError> {
+        if !self.has_battery() {
+            return Err(CartridgeError::NoBattery);
+        }
+        self.mapper.load_save_data(data);
+        self.save_dirty = true;
+        Ok(())
+    }
+
+    // Writes battery RAM to the .sav file if it changed since the last flush
+    pub fn flush_save(&mut self) -> Result<(), CartridgeError> {
+        if !self.save_dirty {
+            return Ok(());
+        }
+        if let Some(save_path) = &self.save_path {
+            fs::write(save_path, self.mapper.save_data())?;
+        }
+        self.save_dirty = false;
+        Ok(())
+    }
+
+    // Whether the rumble motor is on, for hosts that can forward it
+    pub fn rumble(&self) -> bool {
+        self.mapper.rumble()
+    }
+
+    // Whether the infrared LED is lit (HuC1/HuC3)
+    pub fn ir_led(&self) -> bool {
+        self.mapper.ir_led()
+    }
+
+    // Feeds the MBC7 accelerometer, in g along its X and Y axes
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.mapper.set_tilt(x, y)
+    }
+
+    // Feeds the Game Boy Camera sensor a 128x112 grayscale image, 0 is black
+    pub fn set_camera_image(&mut self, image: &[u8]) {
+        self.mapper.set_camera_image(image)
+    }
+}
+
+// Header of an MMM01 multicart's menu, if `rom` is laid out with it at the end
+fn mmm01_header(rom: &[u8]) -> Option<CartridgeHeader> {
+    let menu = rom.get(rom.len().checked_sub(MMM01_MENU_SIZE)?..)?;
+    let header = CartridgeHeader::parse(menu).ok()?;
+    (header.cartridge_type.mapper == MapperType::MMM01).then_some(header)
+}
+
+// Hosts that need to know whether the final write worked call `flush_save`
+// before dropping the cartridge
+impl Drop for Cartridge {
+    fn drop(&mut self) {
+        // The clock kept running since the last write, so its state is saved
+        // even if the game never touched RAM
+        if self.header.cartridge_type.timer && self.has_battery() {
+            self.save_dirty = true;
+        }
+        let _ = self.flush_save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::header::header_checksum;
+
+    // 32 KiB ROM with a valid header
+    fn rom(cartridge_type: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x147] = cartridge_type;
+        rom[0x149] = ram_size;
+        rom[0x14D] = header_checksum(&rom);
+        rom
+    }
+
+    #[test]
+    fn ram_writes_dirty_the_save_only_with_a_battery() {
+        // MBC1+RAM+BATTERY, 8 KiB
+        let mut cartridge = Cartridge::from_bytes(rom(0x03, 0x02)).unwrap();
+        cartridge.write_ram(0xA000, 0x12);
+        assert!(!cartridge.save_dirty, "RAM is disabled");
+        cartridge.write_rom(0x0000, 0x0A);
+        assert!(!cartridge.save_dirty);
+        cartridge.write_ram(0xA000, 0x12);
+        assert!(cartridge.save_dirty);
+
+        // MBC1+RAM
+        let mut cartridge = Cartridge::from_bytes(rom(0x02, 0x02)).unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0xA000, 0x12);
+        assert!(!cartridge.save_dirty);
+    }
+
+    #[test]
+    fn running_clock_does_not_dirty_the_save() {
+        // MBC3+TIMER+RAM+BATTERY
+        let mut cartridge =
+            Cartridge::from_bytes_with_clock(rom(0x10, 0x02), RtcClock::Cycles).unwrap();
+        cartridge.step(SAVE_INTERVAL_CYCLES * 2);
+        assert!(!cartridge.save_dirty);
+
+        // Latching the clock and writing its registers do
+        cartridge.write_rom(0x6000, 0x00);
+        cartridge.write_rom(0x6000, 0x01);
+        assert!(cartridge.save_dirty);
+        cartridge.save_dirty = false;
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_rom(0x4000, 0x08);
+        cartridge.write_ram(0xA000, 30);
+        assert!(cartridge.save_dirty);
+    }
+
+    #[test]
+    fn periodic_save_failure_is_kept() {
+        let mut cartridge = Cartridge::from_bytes(rom(0x03, 0x02)).unwrap();
+        cartridge.save_path = Some(PathBuf::from("/nonexistent/dir/game.sav"));
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0xA000, 0x12);
+        cartridge.step(SAVE_INTERVAL_CYCLES);
+        assert!(matches!(
+            cartridge.last_save_error(),
+            Some(CartridgeError::Io(_))
+        ));
+        cartridge.save_path = None;
+    }
+
+    #[test]
+    fn global_checksum_mismatch_is_reported_without_failing() {
+        let mut rom = rom(0x00, 0x00);
+        let cartridge = Cartridge::from_bytes(rom.clone()).unwrap();
+        assert!(matches!(
+            cartridge.global_checksum_error(),
+            Some(CartridgeError::GlobalChecksumMismatch {
+                expected: 0x0000,
+                actual: _
+            })
+        ));
+
+        let [high, low] = global_checksum(&rom).to_be_bytes();
+        rom[0x14E] = high;
+        rom[0x14F] = low;
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+        assert!(cartridge.global_checksum_error().is_none());
+    }
+
+    #[test]
+    fn mbc6_flash_programming_dirties_the_save() {
+        // MBC6, 32 KiB RAM
+        let mut cartridge = Cartridge::from_bytes(rom(0x20, 0x03)).unwrap();
+        assert_eq!(cartridge.export_save().unwrap().len(), 0x8000);
+
+        // Flash banks 2 and 1 put 0x5555 and 0x2AAA at 0x5555 and 0x6AAA
+        for (addr, value) in [(0x0C00, 1), (0x1000, 1), (0x2000, 2), (0x2800, 0x08)] {
+            cartridge.write_rom(addr, value);
+        }
+        for (addr, value) in [(0x3000, 1), (0x3800, 0x08)] {
+            cartridge.write_rom(addr, value);
+        }
+        for (addr, value) in [(0x5555, 0xAA), (0x6AAA, 0x55), (0x5555, 0xA0)] {
+            cartridge.write_rom(addr, value);
+        }
+        assert!(!cartridge.save_dirty);
+        cartridge.write_rom(0x4000, 0x12);
+        assert!(cartridge.save_dirty);
+        assert_eq!(cartridge.read_rom(0x4000), 0x12);
+
+        let save = cartridge.export_save().unwrap();
+        assert_eq!(save.len(), 0x8000 + 1024 * 1024);
+        assert_eq!(save[0x8000 + 2 * 0x2000], 0x12);
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Machine cycles per second of emulated time
+const CYCLES_PER_SECOND: u32 = 1_048_576;
+
+pub const FOOTER_SIZE: usize = 48;
+// Older saves store the timestamp as 32 bits
+const LEGACY_FOOTER_SIZE: usize = 44;
+
+const DAY_HIGH_BIT: u8 = 0b0000_0001;
+const HALT_BIT: u8 = 0b0100_0000;
+const DAY_CARRY_BIT: u8 = 0b1000_0000;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Where the clock gets its notion of elapsed time from
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RtcClock {
+    Cycles,    // emulated machine cycles, so the clock stops with the emulator
+    WallClock, // host time, so the clock keeps running between sessions
+}
+
+// Snapshot of the five clock registers
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    day_low: u8,
+    day_high: u8, // bit 0: day bit 8, bit 6: halt, bit 7: day carry
+}
+
+impl RtcRegisters {
+    fn new() -> Self {
+        RtcRegisters {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            day_low: 0,
+            day_high: 0,
+        }
+    }
+
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => self.day_low,
+            _ => self.day_high,
+        }
+    }
+
+    fn days(&self) -> u64 {
+        ((self.day_high & DAY_HIGH_BIT) as u64) << 8 | self.day_low as u64
+    }
+
+    fn set_days(&mut self, days: u64) {
+        self.day_low = days as u8;
+        self.day_high = (self.day_high & !DAY_HIGH_BIT) | ((days >> 8) as u8 & DAY_HIGH_BIT);
+    }
+
+    fn is_halted(&self) -> bool {
+        self.day_high & HALT_BIT != 0
+    }
+
+    fn is_valid(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    // Advances one second. Out-of-range values written by software count up
+    // to the register width before wrapping, without carrying.
+    fn tick(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.add_days(1);
+    }
+
+    fn add_days(&mut self, days: u64) {
+        let days = self.days() + days;
+        if days > 0x1FF {
+            self.day_high |= DAY_CARRY_BIT;
+        }
+        self.set_days(days % 0x200);
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        while seconds > 0 && !self.is_valid() {
+            self.tick();
+            seconds -= 1;
+        }
+        if seconds == 0 {
+            return;
+        }
+
+        let time_of_day = self.hours as u64 * 3600 + self.minutes as u64 * 60 + self.seconds as u64;
+        let total = time_of_day + seconds;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        self.add_days(total / SECONDS_PER_DAY);
+    }
+
+    // Each register takes 4 little endian bytes in the save footer
+    fn write_footer(&self, out: &mut Vec<u8>) {
+        for value in [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.day_low,
+            self.day_high,
+        ] {
+            out.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+    }
+
+    fn from_footer(bytes: &[u8]) -> Self {
+        let register = |idx: usize| bytes[idx * 4];
+        RtcRegisters {
+            seconds: register(0) & 0x3F,
+            minutes: register(1) & 0x3F,
+            hours: register(2) & 0x1F,
+            day_low: register(3),
+            day_high: register(4) & (DAY_HIGH_BIT | HALT_BIT | DAY_CARRY_BIT),
+        }
+    }
+}
+
+// MBC3 real-time clock
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rtc {
+    clock: RtcClock,
+    registers: RtcRegisters,
+    latched: RtcRegisters,
+    latch_armed: bool, // a 0x00 write was seen, a 0x01 write latches
+    subsecond_cycles: u32,
+    last_sync: u64, // UNIX time of the last wall clock update
+}
+
+impl Rtc {
+    pub fn new(clock: RtcClock) -> Self {
+        Rtc {
+            clock,
+            registers: RtcRegisters::new(),
+            latched: RtcRegisters::new(),
+            latch_armed: false,
+            subsecond_cycles: 0,
+            last_sync: unix_time(),
+        }
+    }
+
+    pub fn step(&mut self, cycles: u32) {
+        if self.clock != RtcClock::Cycles || self.registers.is_halted() {
+            return;
+        }
+        self.subsecond_cycles += cycles;
+        while self.subsecond_cycles >= CYCLES_PER_SECOND {
+            self.subsecond_cycles -= CYCLES_PER_SECOND;
+            self.registers.tick();
+        }
+    }
+
+    // Running registers caught up with the host clock
+    fn synced_registers(&self, now: u64) -> RtcRegisters {
+        let mut registers = self.registers;
+        if self.clock == RtcClock::WallClock && !registers.is_halted() {
+            registers.advance(now.saturating_sub(self.last_sync));
+        }
+        registers
+    }
+
+    fn sync(&mut self) {
+        let now = unix_time();
+        self.registers = self.synced_registers(now);
+        self.last_sync = now;
+    }
+
+    // Writes to 0x6000-0x7FFF; writing 0x00 then 0x01 latches the current time
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.sync();
+            self.latched = self.registers;
+        }
+        self.latch_armed = value == 0x00;
+    }
+
+    // Reads return the latched time, not the running one
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        self.sync();
+        match register {
+            0x08 => {
+                self.registers.seconds = value & 0x3F;
+                self.subsecond_cycles = 0;
+            }
+            0x09 => self.registers.minutes = value & 0x3F,
+            0x0A => self.registers.hours = value & 0x1F,
+            0x0B => self.registers.day_low = value,
+            _ => self.registers.day_high = value & (DAY_HIGH_BIT | HALT_BIT | DAY_CARRY_BIT),
+        }
+    }
+
+    // 48-byte footer appended to battery RAM by most emulators: the live and
+    // latched registers as 32-bit values, then a 64-bit UNIX timestamp
+    pub fn to_footer(&self) -> Vec<u8> {
+        let now = unix_time();
+        let mut footer = Vec::with_capacity(FOOTER_SIZE);
+        self.synced_registers(now).write_footer(&mut footer);
+        self.latched.write_footer(&mut footer);
+        footer.extend_from_slice(&now.to_le_bytes());
+        footer
+    }
+
+    // Accepts both the 48-byte footer and the older 44-byte one. A footer of
+    // any other size, e.g. a save without one, leaves the clock as it is.
+    pub fn load_footer(&mut self, footer: &[u8]) {
+        let timestamp = match footer.len() {
+            FOOTER_SIZE => u64::from_le_bytes(footer[40..48].try_into().unwrap()),
+            LEGACY_FOOTER_SIZE => u32::from_le_bytes(footer[40..44].try_into().unwrap()) as u64,
+            _ => return,
+        };
+        self.registers = RtcRegisters::from_footer(&footer[0..20]);
+        self.latched = RtcRegisters::from_footer(&footer[20..40]);
+        self.last_sync = timestamp;
+        // Time spent with the emulator closed only counts for the host clock
+        if self.clock == RtcClock::Cycles {
+            self.last_sync = unix_time();
+        }
+        self.sync();
+    }
+}
+
+pub(super) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Halted, so no time passes between saving and loading
+    fn halted_rtc() -> Rtc {
+        let mut rtc = Rtc::new(RtcClock::WallClock);
+        rtc.write(0x0C, HALT_BIT | DAY_HIGH_BIT);
+        for (register, value) in [(0x08, 5), (0x09, 6), (0x0A, 7), (0x0B, 0x20)] {
+            rtc.write(register, value);
+        }
+        rtc.write_latch(0x00);
+        rtc.write_latch(0x01);
+        rtc.write(0x08, 9);
+        rtc
+    }
+
+    #[test]
+    fn footer_round_trips() {
+        let rtc = halted_rtc();
+        let footer = rtc.to_footer();
+        assert_eq!(footer.len(), FOOTER_SIZE);
+        // Live registers, then latched ones, as 32-bit little endian values
+        assert_eq!(footer[0..4], [9, 0, 0, 0]);
+        assert_eq!(footer[20..24], [5, 0, 0, 0]);
+        assert_eq!(footer[16], HALT_BIT | DAY_HIGH_BIT);
+
+        let mut loaded = Rtc::new(RtcClock::WallClock);
+        loaded.load_footer(&footer);
+        assert_eq!(loaded.registers, rtc.registers);
+        assert_eq!(loaded.latched, rtc.latched);
+        assert_eq!(loaded.read(0x08), 5);
+        assert_eq!(loaded.read(0x0B), 0x20);
+    }
+
+    #[test]
+    fn legacy_footer_loads() {
+        let rtc = halted_rtc();
+        let mut footer = rtc.to_footer();
+        footer.truncate(LEGACY_FOOTER_SIZE);
+
+        let mut loaded = Rtc::new(RtcClock::WallClock);
+        loaded.load_footer(&footer);
+        assert_eq!(loaded.registers, rtc.registers);
+        assert_eq!(loaded.latched, rtc.latched);
+    }
+
+    #[test]
+    fn missing_footer_keeps_the_clock() {
+        let rtc = halted_rtc();
+        let mut loaded = rtc.clone();
+        loaded.load_footer(&[]);
+        assert_eq!(loaded.registers, rtc.registers);
+        assert_eq!(loaded.latched, rtc.latched);
+    }
+}
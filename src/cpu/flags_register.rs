@@ -22,6 +22,12 @@ impl FlagsRegister {
     }
 }
 
+impl Default for FlagsRegister {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl std::convert::From<FlagsRegister> for u8 {
     fn from(flags: FlagsRegister) -> Self {
         (if flags.zero { 1 } else { 0 }) << ZERO_FLAG_BYTE_POSITION
@@ -1,5 +1,6 @@
 use super::targets::{
-    ADDHLTarget, ArithmeticTarget, IncDecTarget, JumpTest, LoadType, StackTarget,
+    ADDHLTarget, ArithmeticTarget, IncDecTarget, Indirect, JumpTest, LoadByteSource,
+    LoadByteTarget, LoadType, LoadWordTarget, PrefixTarget, StackTarget,
 };
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -9,6 +10,7 @@ pub enum Instruction {
 
     ADD(ArithmeticTarget), // add
     ADDHL(ADDHLTarget),    // add to HL
+    ADDSP,                 // add signed 8-bit value to SP
     ADC(ArithmeticTarget), // add with carry
     SUB(ArithmeticTarget), // subtract
     SBC(ArithmeticTarget), // subtract with carry
@@ -18,32 +20,34 @@ pub enum Instruction {
     XOR(ArithmeticTarget), // logical XOR
     CP(ArithmeticTarget),  // compare
 
-    CCF(ArithmeticTarget), // complement carry flag
-    SCF(ArithmeticTarget), // set carry flag
+    CCF, // complement carry flag
+    SCF, // set carry flag
 
-    RRA(ArithmeticTarget),  // rotate right A register
-    RLA(ArithmeticTarget),  // rotate left A register
-    RRCA(ArithmeticTarget), // rotate right A register (no carry)
-    RRLA(ArithmeticTarget), // rotate left A register (no carry)
-    CPL(ArithmeticTarget),  // complement
+    RRA,  // rotate right A register
+    RLA,  // rotate left A register
+    RRCA, // rotate right A register (no carry)
+    RLCA, // rotate left A register (no carry)
+    CPL,  // complement
+    DAA,  // decimal adjust A register
 
-    BIT(ArithmeticTarget),   // bit test
-    RESET(ArithmeticTarget), // bit reset
-    SET(ArithmeticTarget),   // bit set
+    BIT(u8, PrefixTarget),   // bit test
+    RESET(u8, PrefixTarget), // bit reset
+    SET(u8, PrefixTarget),   // bit set
 
-    SRL(ArithmeticTarget), // shift right logical
-    SLL(ArithmeticTarget), // shift left logical
-    SRA(ArithmeticTarget), // shift right arithmetic
-    SLA(ArithmeticTarget), // shift left arithmetic
+    SRL(PrefixTarget), // shift right logical
+    SRA(PrefixTarget), // shift right arithmetic
+    SLA(PrefixTarget), // shift left arithmetic
 
-    RR(ArithmeticTarget),  // rotate right
-    RL(ArithmeticTarget),  // rotate left
-    RRC(ArithmeticTarget), // rotate right (no carry)
-    RLC(ArithmeticTarget), // rotate left (no carry)
+    RR(PrefixTarget),  // rotate right
+    RL(PrefixTarget),  // rotate left
+    RRC(PrefixTarget), // rotate right (no carry)
+    RLC(PrefixTarget), // rotate left (no carry)
 
-    SWAP(ArithmeticTarget), // swap nibbles
+    SWAP(PrefixTarget), // swap nibbles
 
     JP(JumpTest), // jump
+    JPHL,         // jump to address in HL
+    JR(JumpTest), // relative jump
     LD(LoadType), // load
 
     PUSH(StackTarget), // push to stack
@@ -51,52 +55,847 @@ pub enum Instruction {
 
     CALL(JumpTest), // call
     RET(JumpTest),  // return
+    RETI,           // return and enable interrupts
+    RST(u8),        // call fixed restart vector
 
     NOP,  // no operation
     HALT, // halt
+    STOP, // stop
+    DI,   // disable interrupts
+    EI,   // enable interrupts
+
+    ILLEGAL(u8), // unused opcode, locks up the CPU
 }
 
 impl Instruction {
+    // Decodes an unprefixed opcode. Only the 0xCB prefix gives `None`, as the
+    // byte after it identifies the instruction.
     pub fn from_byte(byte: u8) -> Option<Self> {
         match byte {
-            0x3c => Some(Instruction::INC(IncDecTarget::A)),
+            0x00 => Some(Instruction::NOP),
+            0x10 => Some(Instruction::STOP),
+            0x76 => Some(Instruction::HALT),
+            0xf3 => Some(Instruction::DI),
+            0xfb => Some(Instruction::EI),
+
+            0x03 => Some(Instruction::INC(IncDecTarget::BC)),
             0x04 => Some(Instruction::INC(IncDecTarget::B)),
-            0x14 => Some(Instruction::INC(IncDecTarget::D)),
-            0x24 => Some(Instruction::INC(IncDecTarget::H)),
             0x0c => Some(Instruction::INC(IncDecTarget::C)),
-            0x1c => Some(Instruction::INC(IncDecTarget::E)),
-            0x2c => Some(Instruction::INC(IncDecTarget::L)),
-            0x03 => Some(Instruction::INC(IncDecTarget::BC)),
             0x13 => Some(Instruction::INC(IncDecTarget::DE)),
+            0x14 => Some(Instruction::INC(IncDecTarget::D)),
+            0x1c => Some(Instruction::INC(IncDecTarget::E)),
             0x23 => Some(Instruction::INC(IncDecTarget::HL)),
+            0x24 => Some(Instruction::INC(IncDecTarget::H)),
+            0x2c => Some(Instruction::INC(IncDecTarget::L)),
             0x33 => Some(Instruction::INC(IncDecTarget::SP)),
+            0x34 => Some(Instruction::INC(IncDecTarget::HLI)),
+            0x3c => Some(Instruction::INC(IncDecTarget::A)),
 
-            0x3d => Some(Instruction::DEC(IncDecTarget::A)),
             0x05 => Some(Instruction::DEC(IncDecTarget::B)),
+            0x0b => Some(Instruction::DEC(IncDecTarget::BC)),
             0x0d => Some(Instruction::DEC(IncDecTarget::C)),
             0x15 => Some(Instruction::DEC(IncDecTarget::D)),
+            0x1b => Some(Instruction::DEC(IncDecTarget::DE)),
             0x1d => Some(Instruction::DEC(IncDecTarget::E)),
             0x25 => Some(Instruction::DEC(IncDecTarget::H)),
-            0x2d => Some(Instruction::DEC(IncDecTarget::L)),
-            0x0b => Some(Instruction::DEC(IncDecTarget::BC)),
-            0x1b => Some(Instruction::DEC(IncDecTarget::DE)),
             0x2b => Some(Instruction::DEC(IncDecTarget::HL)),
+            0x2d => Some(Instruction::DEC(IncDecTarget::L)),
+            0x35 => Some(Instruction::DEC(IncDecTarget::HLI)),
             0x3b => Some(Instruction::DEC(IncDecTarget::SP)),
+            0x3d => Some(Instruction::DEC(IncDecTarget::A)),
 
-            0x87 => Some(Instruction::ADD(ArithmeticTarget::A)),
             0x80 => Some(Instruction::ADD(ArithmeticTarget::B)),
             0x81 => Some(Instruction::ADD(ArithmeticTarget::C)),
             0x82 => Some(Instruction::ADD(ArithmeticTarget::D)),
             0x83 => Some(Instruction::ADD(ArithmeticTarget::E)),
             0x84 => Some(Instruction::ADD(ArithmeticTarget::H)),
             0x85 => Some(Instruction::ADD(ArithmeticTarget::L)),
+            0x86 => Some(Instruction::ADD(ArithmeticTarget::HLI)),
+            0x87 => Some(Instruction::ADD(ArithmeticTarget::A)),
+            0xc6 => Some(Instruction::ADD(ArithmeticTarget::D8)),
 
             0x09 => Some(Instruction::ADDHL(ADDHLTarget::BC)),
             0x19 => Some(Instruction::ADDHL(ADDHLTarget::DE)),
             0x29 => Some(Instruction::ADDHL(ADDHLTarget::HL)),
             0x39 => Some(Instruction::ADDHL(ADDHLTarget::SP)),
+            0xe8 => Some(Instruction::ADDSP),
+
+            0x88 => Some(Instruction::ADC(ArithmeticTarget::B)),
+            0x89 => Some(Instruction::ADC(ArithmeticTarget::C)),
+            0x8a => Some(Instruction::ADC(ArithmeticTarget::D)),
+            0x8b => Some(Instruction::ADC(ArithmeticTarget::E)),
+            0x8c => Some(Instruction::ADC(ArithmeticTarget::H)),
+            0x8d => Some(Instruction::ADC(ArithmeticTarget::L)),
+            0x8e => Some(Instruction::ADC(ArithmeticTarget::HLI)),
+            0x8f => Some(Instruction::ADC(ArithmeticTarget::A)),
+            0xce => Some(Instruction::ADC(ArithmeticTarget::D8)),
+
+            0x90 => Some(Instruction::SUB(ArithmeticTarget::B)),
+            0x91 => Some(Instruction::SUB(ArithmeticTarget::C)),
+            0x92 => Some(Instruction::SUB(ArithmeticTarget::D)),
+            0x93 => Some(Instruction::SUB(ArithmeticTarget::E)),
+            0x94 => Some(Instruction::SUB(ArithmeticTarget::H)),
+            0x95 => Some(Instruction::SUB(ArithmeticTarget::L)),
+            0x96 => Some(Instruction::SUB(ArithmeticTarget::HLI)),
+            0x97 => Some(Instruction::SUB(ArithmeticTarget::A)),
+            0xd6 => Some(Instruction::SUB(ArithmeticTarget::D8)),
+
+            0x98 => Some(Instruction::SBC(ArithmeticTarget::B)),
+            0x99 => Some(Instruction::SBC(ArithmeticTarget::C)),
+            0x9a => Some(Instruction::SBC(ArithmeticTarget::D)),
+            0x9b => Some(Instruction::SBC(ArithmeticTarget::E)),
+            0x9c => Some(Instruction::SBC(ArithmeticTarget::H)),
+            0x9d => Some(Instruction::SBC(ArithmeticTarget::L)),
+            0x9e => Some(Instruction::SBC(ArithmeticTarget::HLI)),
+            0x9f => Some(Instruction::SBC(ArithmeticTarget::A)),
+            0xde => Some(Instruction::SBC(ArithmeticTarget::D8)),
+
+            0xa0 => Some(Instruction::AND(ArithmeticTarget::B)),
+            0xa1 => Some(Instruction::AND(ArithmeticTarget::C)),
+            0xa2 => Some(Instruction::AND(ArithmeticTarget::D)),
+            0xa3 => Some(Instruction::AND(ArithmeticTarget::E)),
+            0xa4 => Some(Instruction::AND(ArithmeticTarget::H)),
+            0xa5 => Some(Instruction::AND(ArithmeticTarget::L)),
+            0xa6 => Some(Instruction::AND(ArithmeticTarget::HLI)),
+            0xa7 => Some(Instruction::AND(ArithmeticTarget::A)),
+            0xe6 => Some(Instruction::AND(ArithmeticTarget::D8)),
+
+            0xa8 => Some(Instruction::XOR(ArithmeticTarget::B)),
+            0xa9 => Some(Instruction::XOR(ArithmeticTarget::C)),
+            0xaa => Some(Instruction::XOR(ArithmeticTarget::D)),
+            0xab => Some(Instruction::XOR(ArithmeticTarget::E)),
+            0xac => Some(Instruction::XOR(ArithmeticTarget::H)),
+            0xad => Some(Instruction::XOR(ArithmeticTarget::L)),
+            0xae => Some(Instruction::XOR(ArithmeticTarget::HLI)),
+            0xaf => Some(Instruction::XOR(ArithmeticTarget::A)),
+            0xee => Some(Instruction::XOR(ArithmeticTarget::D8)),
+
+            0xb0 => Some(Instruction::OR(ArithmeticTarget::B)),
+            0xb1 => Some(Instruction::OR(ArithmeticTarget::C)),
+            0xb2 => Some(Instruction::OR(ArithmeticTarget::D)),
+            0xb3 => Some(Instruction::OR(ArithmeticTarget::E)),
+            0xb4 => Some(Instruction::OR(ArithmeticTarget::H)),
+            0xb5 => Some(Instruction::OR(ArithmeticTarget::L)),
+            0xb6 => Some(Instruction::OR(ArithmeticTarget::HLI)),
+            0xb7 => Some(Instruction::OR(ArithmeticTarget::A)),
+            0xf6 => Some(Instruction::OR(ArithmeticTarget::D8)),
+
+            0xb8 => Some(Instruction::CP(ArithmeticTarget::B)),
+            0xb9 => Some(Instruction::CP(ArithmeticTarget::C)),
+            0xba => Some(Instruction::CP(ArithmeticTarget::D)),
+            0xbb => Some(Instruction::CP(ArithmeticTarget::E)),
+            0xbc => Some(Instruction::CP(ArithmeticTarget::H)),
+            0xbd => Some(Instruction::CP(ArithmeticTarget::L)),
+            0xbe => Some(Instruction::CP(ArithmeticTarget::HLI)),
+            0xbf => Some(Instruction::CP(ArithmeticTarget::A)),
+            0xfe => Some(Instruction::CP(ArithmeticTarget::D8)),
+
+            0x27 => Some(Instruction::DAA),
+            0x2f => Some(Instruction::CPL),
+            0x3f => Some(Instruction::CCF),
+            0x37 => Some(Instruction::SCF),
+
+            0x07 => Some(Instruction::RLCA),
+            0x17 => Some(Instruction::RLA),
+            0x0f => Some(Instruction::RRCA),
+            0x1f => Some(Instruction::RRA),
+
+            0xc2 => Some(Instruction::JP(JumpTest::NotZero)),
+            0xc3 => Some(Instruction::JP(JumpTest::Always)),
+            0xca => Some(Instruction::JP(JumpTest::Zero)),
+            0xd2 => Some(Instruction::JP(JumpTest::NotCarry)),
+            0xda => Some(Instruction::JP(JumpTest::Carry)),
+            0xe9 => Some(Instruction::JPHL),
+
+            0x18 => Some(Instruction::JR(JumpTest::Always)),
+            0x20 => Some(Instruction::JR(JumpTest::NotZero)),
+            0x28 => Some(Instruction::JR(JumpTest::Zero)),
+            0x30 => Some(Instruction::JR(JumpTest::NotCarry)),
+            0x38 => Some(Instruction::JR(JumpTest::Carry)),
+
+            0xc4 => Some(Instruction::CALL(JumpTest::NotZero)),
+            0xcc => Some(Instruction::CALL(JumpTest::Zero)),
+            0xcd => Some(Instruction::CALL(JumpTest::Always)),
+            0xd4 => Some(Instruction::CALL(JumpTest::NotCarry)),
+            0xdc => Some(Instruction::CALL(JumpTest::Carry)),
+
+            0xc0 => Some(Instruction::RET(JumpTest::NotZero)),
+            0xc8 => Some(Instruction::RET(JumpTest::Zero)),
+            0xc9 => Some(Instruction::RET(JumpTest::Always)),
+            0xd0 => Some(Instruction::RET(JumpTest::NotCarry)),
+            0xd8 => Some(Instruction::RET(JumpTest::Carry)),
+            0xd9 => Some(Instruction::RETI),
+
+            0xc7 => Some(Instruction::RST(0x00)),
+            0xcf => Some(Instruction::RST(0x08)),
+            0xd7 => Some(Instruction::RST(0x10)),
+            0xdf => Some(Instruction::RST(0x18)),
+            0xe7 => Some(Instruction::RST(0x20)),
+            0xef => Some(Instruction::RST(0x28)),
+            0xf7 => Some(Instruction::RST(0x30)),
+            0xff => Some(Instruction::RST(0x38)),
+
+            0xc5 => Some(Instruction::PUSH(StackTarget::BC)),
+            0xd5 => Some(Instruction::PUSH(StackTarget::DE)),
+            0xe5 => Some(Instruction::PUSH(StackTarget::HL)),
+            0xf5 => Some(Instruction::PUSH(StackTarget::AF)),
+
+            0xc1 => Some(Instruction::POP(StackTarget::BC)),
+            0xd1 => Some(Instruction::POP(StackTarget::DE)),
+            0xe1 => Some(Instruction::POP(StackTarget::HL)),
+            0xf1 => Some(Instruction::POP(StackTarget::AF)),
+
+            0x06 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::D8,
+            ))),
+            0x40 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::B,
+            ))),
+            0x41 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::C,
+            ))),
+            0x42 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::D,
+            ))),
+            0x43 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::E,
+            ))),
+            0x44 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::H,
+            ))),
+            0x45 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::L,
+            ))),
+            0x46 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::HLI,
+            ))),
+            0x47 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::B,
+                LoadByteSource::A,
+            ))),
+
+            0x0e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::D8,
+            ))),
+            0x48 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::B,
+            ))),
+            0x49 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::C,
+            ))),
+            0x4a => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::D,
+            ))),
+            0x4b => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::E,
+            ))),
+            0x4c => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::H,
+            ))),
+            0x4d => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::L,
+            ))),
+            0x4e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::HLI,
+            ))),
+            0x4f => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::C,
+                LoadByteSource::A,
+            ))),
+
+            0x16 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::D8,
+            ))),
+            0x50 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::B,
+            ))),
+            0x51 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::C,
+            ))),
+            0x52 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::D,
+            ))),
+            0x53 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::E,
+            ))),
+            0x54 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::H,
+            ))),
+            0x55 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::L,
+            ))),
+            0x56 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::HLI,
+            ))),
+            0x57 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::D,
+                LoadByteSource::A,
+            ))),
+
+            0x1e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::D8,
+            ))),
+            0x58 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::B,
+            ))),
+            0x59 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::C,
+            ))),
+            0x5a => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::D,
+            ))),
+            0x5b => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::E,
+            ))),
+            0x5c => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::H,
+            ))),
+            0x5d => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::L,
+            ))),
+            0x5e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::HLI,
+            ))),
+            0x5f => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::E,
+                LoadByteSource::A,
+            ))),
+
+            0x26 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::D8,
+            ))),
+            0x60 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::B,
+            ))),
+            0x61 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::C,
+            ))),
+            0x62 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::D,
+            ))),
+            0x63 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::E,
+            ))),
+            0x64 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::H,
+            ))),
+            0x65 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::L,
+            ))),
+            0x66 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::HLI,
+            ))),
+            0x67 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::H,
+                LoadByteSource::A,
+            ))),
+
+            0x2e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::D8,
+            ))),
+            0x68 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::B,
+            ))),
+            0x69 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::C,
+            ))),
+            0x6a => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::D,
+            ))),
+            0x6b => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::E,
+            ))),
+            0x6c => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::H,
+            ))),
+            0x6d => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::L,
+            ))),
+            0x6e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::HLI,
+            ))),
+            0x6f => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::L,
+                LoadByteSource::A,
+            ))),
+
+            0x36 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::D8,
+            ))),
+            0x70 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::B,
+            ))),
+            0x71 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::C,
+            ))),
+            0x72 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::D,
+            ))),
+            0x73 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::E,
+            ))),
+            0x74 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::H,
+            ))),
+            0x75 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::L,
+            ))),
+            0x77 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::HLI,
+                LoadByteSource::A,
+            ))),
+
+            0x3e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::D8,
+            ))),
+            0x78 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::B,
+            ))),
+            0x79 => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::C,
+            ))),
+            0x7a => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::D,
+            ))),
+            0x7b => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::E,
+            ))),
+            0x7c => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::H,
+            ))),
+            0x7d => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::L,
+            ))),
+            0x7e => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::HLI,
+            ))),
+            0x7f => Some(Instruction::LD(LoadType::Byte(
+                LoadByteTarget::A,
+                LoadByteSource::A,
+            ))),
+
+            0x01 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::BC))),
+            0x11 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::DE))),
+            0x21 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::HL))),
+            0x31 => Some(Instruction::LD(LoadType::Word(LoadWordTarget::SP))),
+
+            0x02 => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::BCIndirect,
+            ))),
+            0x12 => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::DEIndirect,
+            ))),
+            0x22 => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::HLIndirectPlus,
+            ))),
+            0x32 => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::HLIndirectMinus,
+            ))),
+            0xe2 => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::LastByteIndirect,
+            ))),
+            0xea => Some(Instruction::LD(LoadType::IndirectFromA(
+                Indirect::WordIndirect,
+            ))),
+
+            0x0a => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::BCIndirect,
+            ))),
+            0x1a => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::DEIndirect,
+            ))),
+            0x2a => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::HLIndirectPlus,
+            ))),
+            0x3a => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::HLIndirectMinus,
+            ))),
+            0xf2 => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::LastByteIndirect,
+            ))),
+            0xfa => Some(Instruction::LD(LoadType::AFromIndirect(
+                Indirect::WordIndirect,
+            ))),
+
+            0xe0 => Some(Instruction::LD(LoadType::ByteAddressFromA)),
+
+            0xf0 => Some(Instruction::LD(LoadType::AFromByteAddress)),
+
+            0x08 => Some(Instruction::LD(LoadType::IndirectFromSP)),
+
+            0xf9 => Some(Instruction::LD(LoadType::SPFromHL)),
+
+            0xf8 => Some(Instruction::LD(LoadType::HLFromSPN)),
+
+            0xd3 | 0xdb | 0xdd | 0xe3 | 0xe4 | 0xeb | 0xec | 0xed | 0xf4 | 0xfc | 0xfd => {
+                Some(Instruction::ILLEGAL(byte))
+            }
+
+            0xcb => None,
+        }
+    }
+
+    // Decodes the byte following a 0xCB prefix
+    pub fn from_prefixed_byte(byte: u8) -> Self {
+        // Opcode layout: bits 7-6 select the group, bits 5-3 the operation or bit
+        // index, bits 2-0 the target
+        let target = match byte & 0b111 {
+            0 => PrefixTarget::B,
+            1 => PrefixTarget::C,
+            2 => PrefixTarget::D,
+            3 => PrefixTarget::E,
+            4 => PrefixTarget::H,
+            5 => PrefixTarget::L,
+            6 => PrefixTarget::HLI,
+            _ => PrefixTarget::A,
+        };
+        let bit = (byte >> 3) & 0b111;
+
+        match byte >> 6 {
+            0b00 => match bit {
+                0 => Instruction::RLC(target),
+                1 => Instruction::RRC(target),
+                2 => Instruction::RL(target),
+                3 => Instruction::RR(target),
+                4 => Instruction::SLA(target),
+                5 => Instruction::SRA(target),
+                6 => Instruction::SWAP(target),
+                _ => Instruction::SRL(target),
+            },
+            0b01 => Instruction::BIT(bit, target),
+            0b10 => Instruction::RESET(bit, target),
+            _ => Instruction::SET(bit, target),
+        }
+    }
+
+    // Bytes the instruction occupies, including any 0xCB prefix
+    pub fn length(&self) -> u16 {
+        match self {
+            Instruction::BIT(_, _)
+            | Instruction::RESET(_, _)
+            | Instruction::SET(_, _)
+            | Instruction::SRL(_)
+            | Instruction::SRA(_)
+            | Instruction::SLA(_)
+            | Instruction::RR(_)
+            | Instruction::RL(_)
+            | Instruction::RRC(_)
+            | Instruction::RLC(_)
+            | Instruction::SWAP(_) => 2,
+
+            Instruction::ADD(target)
+            | Instruction::ADC(target)
+            | Instruction::SUB(target)
+            | Instruction::SBC(target)
+            | Instruction::AND(target)
+            | Instruction::OR(target)
+            | Instruction::XOR(target)
+            | Instruction::CP(target) => match target {
+                ArithmeticTarget::D8 => 2,
+                _ => 1,
+            },
+
+            Instruction::LD(load_type) => match load_type {
+                LoadType::Byte(_, LoadByteSource::D8) => 2,
+                LoadType::Byte(_, _) => 1,
+                LoadType::Word(_) => 3,
+                LoadType::AFromIndirect(Indirect::WordIndirect)
+                | LoadType::IndirectFromA(Indirect::WordIndirect) => 3,
+                LoadType::AFromIndirect(_) | LoadType::IndirectFromA(_) => 1,
+                LoadType::AFromByteAddress | LoadType::ByteAddressFromA => 2,
+                LoadType::IndirectFromSP => 3,
+                LoadType::SPFromHL => 1,
+                LoadType::HLFromSPN => 2,
+            },
+
+            Instruction::JP(_) | Instruction::CALL(_) => 3,
+            Instruction::JR(_) | Instruction::ADDSP | Instruction::STOP => 2,
+
+            _ => 1,
+        }
+    }
+
+    // Machine cycles the instruction takes. `branch_taken` selects the longer
+    // timing of conditional jumps, calls and returns.
+    pub fn cycles(&self, branch_taken: bool) -> u8 {
+        match self {
+            Instruction::INC(target) | Instruction::DEC(target) => match target {
+                IncDecTarget::HLI => 3,
+                IncDecTarget::BC | IncDecTarget::DE | IncDecTarget::HL | IncDecTarget::SP => 2,
+                _ => 1,
+            },
+
+            Instruction::ADD(target)
+            | Instruction::ADC(target)
+            | Instruction::SUB(target)
+            | Instruction::SBC(target)
+            | Instruction::AND(target)
+            | Instruction::OR(target)
+            | Instruction::XOR(target)
+            | Instruction::CP(target) => match target {
+                ArithmeticTarget::HLI | ArithmeticTarget::D8 => 2,
+                _ => 1,
+            },
+            Instruction::ADDHL(_) => 2,
+            Instruction::ADDSP => 4,
+
+            Instruction::BIT(_, target) => match target {
+                PrefixTarget::HLI => 3,
+                _ => 2,
+            },
+            Instruction::RESET(_, target)
+            | Instruction::SET(_, target)
+            | Instruction::SRL(target)
+            | Instruction::SRA(target)
+            | Instruction::SLA(target)
+            | Instruction::RR(target)
+            | Instruction::RL(target)
+            | Instruction::RRC(target)
+            | Instruction::RLC(target)
+            | Instruction::SWAP(target) => match target {
+                PrefixTarget::HLI => 4,
+                _ => 2,
+            },
+
+            Instruction::LD(load_type) => match load_type {
+                LoadType::Byte(LoadByteTarget::HLI, LoadByteSource::D8) => 3,
+                LoadType::Byte(LoadByteTarget::HLI, _)
+                | LoadType::Byte(_, LoadByteSource::HLI)
+                | LoadType::Byte(_, LoadByteSource::D8) => 2,
+                LoadType::Byte(_, _) => 1,
+                LoadType::Word(_) => 3,
+                LoadType::AFromIndirect(Indirect::WordIndirect)
+                | LoadType::IndirectFromA(Indirect::WordIndirect) => 4,
+                LoadType::AFromIndirect(_) | LoadType::IndirectFromA(_) => 2,
+                LoadType::AFromByteAddress | LoadType::ByteAddressFromA => 3,
+                LoadType::IndirectFromSP => 5,
+                LoadType::SPFromHL => 2,
+                LoadType::HLFromSPN => 3,
+            },
+
+            Instruction::JP(_) => {
+                if branch_taken {
+                    4
+                } else {
+                    3
+                }
+            }
+            Instruction::JR(_) => {
+                if branch_taken {
+                    3
+                } else {
+                    2
+                }
+            }
+            Instruction::CALL(_) => {
+                if branch_taken {
+                    6
+                } else {
+                    3
+                }
+            }
+            // Unconditional RET skips the condition check cycle
+            Instruction::RET(JumpTest::Always) => 4,
+            Instruction::RET(_) => {
+                if branch_taken {
+                    5
+                } else {
+                    2
+                }
+            }
+            Instruction::RETI | Instruction::RST(_) | Instruction::PUSH(_) => 4,
+            Instruction::POP(_) => 3,
+
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_unprefixed_opcode_decodes() {
+        const ILLEGAL: [u8; 11] = [
+            0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD,
+        ];
+        for byte in 0..=0xFF {
+            match Instruction::from_byte(byte) {
+                None => assert_eq!(byte, 0xCB),
+                Some(Instruction::ILLEGAL(opcode)) => {
+                    assert_eq!(opcode, byte);
+                    assert!(ILLEGAL.contains(&byte), "{:#04X}", byte);
+                }
+                Some(_) => assert!(!ILLEGAL.contains(&byte), "{:#04X}", byte),
+            }
+        }
+    }
+
+    // Lengths of the unprefixed opcodes, row by high nibble. 0xCB and the
+    // illegal opcodes hold 0.
+    #[rustfmt::skip]
+    const LENGTHS: [u16; 256] = [
+        1, 3, 1, 1, 1, 1, 2, 1, 3, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        2, 3, 1, 1, 1, 1, 2, 1, 2, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 3, 3, 3, 1, 2, 1, 1, 1, 3, 0, 3, 3, 2, 1,
+        1, 1, 3, 0, 3, 1, 2, 1, 1, 1, 3, 0, 3, 0, 2, 1,
+        2, 1, 1, 0, 0, 1, 2, 1, 2, 1, 3, 0, 0, 0, 2, 1,
+        2, 1, 1, 1, 0, 1, 2, 1, 2, 1, 3, 1, 0, 0, 2, 1,
+    ];
+
+    #[test]
+    fn lengths_match_the_opcode_table() {
+        for byte in 0..=0xFF {
+            let expected = LENGTHS[byte as usize];
+            match Instruction::from_byte(byte) {
+                Some(Instruction::ILLEGAL(_)) | None => assert_eq!(expected, 0),
+                Some(instruction) => {
+                    assert_eq!(instruction.length(), expected, "{:#04X}", byte)
+                }
+            }
+            assert_eq!(Instruction::from_prefixed_byte(byte).length(), 2);
+        }
+    }
+
+    // Machine cycles of the unprefixed opcodes with any branch taken
+    #[rustfmt::skip]
+    const CYCLES: [u8; 256] = [
+        1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1,
+        1, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+        3, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+        3, 3, 2, 2, 3, 3, 3, 1, 3, 2, 2, 2, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        2, 2, 2, 2, 2, 2, 1, 2, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1,
+        5, 3, 4, 4, 6, 4, 2, 4, 5, 4, 4, 0, 6, 6, 2, 4,
+        5, 3, 4, 0, 6, 4, 2, 4, 5, 4, 4, 0, 6, 0, 2, 4,
+        3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4,
+        3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4,
+    ];
+
+    #[test]
+    fn taken_cycles_match_the_opcode_table() {
+        for byte in 0..=0xFF {
+            let expected = CYCLES[byte as usize];
+            match Instruction::from_byte(byte) {
+                Some(Instruction::ILLEGAL(_)) | None => assert_eq!(expected, 0),
+                Some(instruction) => {
+                    assert_eq!(instruction.cycles(true), expected, "{:#04X}", byte)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conditional_branches_are_shorter_when_not_taken() {
+        // JR cc, RET cc, JP cc and CALL cc
+        let not_taken = [
+            ([0x20, 0x28, 0x30, 0x38], 2),
+            ([0xC0, 0xC8, 0xD0, 0xD8], 2),
+            ([0xC2, 0xCA, 0xD2, 0xDA], 3),
+            ([0xC4, 0xCC, 0xD4, 0xDC], 3),
+        ];
+        for (opcodes, cycles) in not_taken {
+            for byte in opcodes {
+                let instruction = Instruction::from_byte(byte).unwrap();
+                assert_eq!(instruction.cycles(false), cycles, "{:#04X}", byte);
+            }
+        }
+        // RET always pays for the pop, with or without a condition to check
+        let ret = Instruction::from_byte(0xC9).unwrap();
+        assert_eq!(ret.cycles(false), 4);
+    }
 
-            _ => None,
+    #[test]
+    fn prefixed_cycles_depend_on_the_target() {
+        for byte in 0..=0xFF {
+            let expected = match (byte & 0b111, byte >> 6) {
+                // (HL): BIT only reads, the rest read and write back
+                (0b110, 0b01) => 3,
+                (0b110, _) => 4,
+                _ => 2,
+            };
+            let instruction = Instruction::from_prefixed_byte(byte);
+            assert_eq!(instruction.cycles(false), expected, "CB {:#04X}", byte);
         }
     }
 }
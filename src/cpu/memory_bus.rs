@@ -1,32 +1,623 @@
-use crate::gpu::{GPU, VRAM_BEGIN, VRAM_END};
+use crate::cartridge::header::CgbSupport;
+use crate::cartridge::Cartridge;
+use crate::gpu::{
+    BCPD_ADDRESS, BCPS_ADDRESS, BGP_ADDRESS, GPU, LCDC_ADDRESS, LYC_ADDRESS, OAM_BEGIN, OAM_END,
+    OAM_SIZE, OBP1_ADDRESS, OCPD_ADDRESS, VBK_ADDRESS, VRAM_BEGIN, VRAM_END, WX_ADDRESS,
+    WY_ADDRESS,
+};
+use crate::interrupts::{Interrupt, InterruptFlags, IE_ADDRESS, IF_ADDRESS};
+use crate::model::{BootRomError, Model};
+use crate::timer::{Timer, DIV_ADDRESS, TAC_ADDRESS};
 
+pub const ROM_BANK_0_BEGIN: usize = 0x0000;
+pub const ROM_BANK_0_END: usize = 0x3FFF;
+pub const ROM_BANK_N_BEGIN: usize = 0x4000;
+pub const ROM_BANK_N_END: usize = 0x7FFF;
+
+pub const EXTERNAL_RAM_BEGIN: usize = 0xA000;
+pub const EXTERNAL_RAM_END: usize = 0xBFFF;
+
+pub const WORKING_RAM_BEGIN: usize = 0xC000;
+pub const WORKING_RAM_END: usize = 0xDFFF;
+// 0xC000-0xCFFF is always bank 0; 0xD000-0xDFFF shows bank 1, or on CGB
+// whichever of banks 1-7 SVBK selects
+const WORKING_RAM_BANK_SIZE: usize = 0x1000;
+const WORKING_RAM_BANKS: usize = 8;
+pub const SVBK_ADDRESS: usize = 0xFF70;
+
+// Mirror of 0xC000-0xDDFF
+pub const ECHO_RAM_BEGIN: usize = 0xE000;
+pub const ECHO_RAM_END: usize = 0xFDFF;
+
+pub const UNUSABLE_BEGIN: usize = 0xFEA0;
+pub const UNUSABLE_END: usize = 0xFEFF;
+
+pub const IO_REGISTERS_BEGIN: usize = 0xFF00;
+pub const IO_REGISTERS_END: usize = 0xFF7F;
+pub const IO_REGISTERS_SIZE: usize = IO_REGISTERS_END - IO_REGISTERS_BEGIN + 1;
+
+// Writing a non-zero value unmaps the boot ROM until the next power cycle
+pub const BOOT_ROM_DISABLE_ADDRESS: usize = 0xFF50;
+
+// Writing a value starts copying 0xXX00-0xXX9F into OAM
+pub const DMA_ADDRESS: usize = 0xFF46;
+// M-cycles between the DMA register write and the first byte being copied
+const DMA_START_DELAY: u8 = 1;
+
+pub const HIGH_RAM_BEGIN: usize = 0xFF80;
+pub const HIGH_RAM_END: usize = 0xFFFE;
+pub const HIGH_RAM_SIZE: usize = HIGH_RAM_END - HIGH_RAM_BEGIN + 1;
+
+// OAM DMA copying one byte per machine cycle
 #[derive(Copy, Clone, Debug, PartialEq)]
+struct DmaTransfer {
+    source: u16,
+    index: usize, // next OAM byte to write
+}
+
+#[derive(Debug)]
 pub struct MemoryBus {
-    pub memory: [u8; 0xFFFF],
+    model: Model,
+    boot_rom: Option<Vec<u8>>, // mapped over the cartridge until disabled
+    cartridge: Option<Cartridge>,
+    working_ram: [u8; WORKING_RAM_BANK_SIZE * WORKING_RAM_BANKS],
+    working_ram_bank: u8, // SVBK
+    io_registers: [u8; IO_REGISTERS_SIZE],
+    high_ram: [u8; HIGH_RAM_SIZE],
+    dma_register: u8,
+    dma: Option<DmaTransfer>,
+    dma_pending: Option<(u16, u8)>, // source and remaining start delay
+    ppu_access_restrictions: bool,  // VRAM/OAM locking and the OAM bug
+    cgb_mode: bool,
     pub gpu: GPU,
+    pub timer: Timer,
+    pub interrupt_enable: InterruptFlags, // IE
+    pub interrupt_flag: InterruptFlags,   // IF
 }
 
 impl MemoryBus {
-    pub fn new() -> Self {
+    pub fn new(model: Model) -> Self {
         MemoryBus {
-            memory: [0; 0xFFFF],
+            model,
+            boot_rom: None,
+            cartridge: None,
+            working_ram: [0; WORKING_RAM_BANK_SIZE * WORKING_RAM_BANKS],
+            working_ram_bank: 0,
+            io_registers: [0xFF; IO_REGISTERS_SIZE],
+            high_ram: [0; HIGH_RAM_SIZE],
+            dma_register: 0xFF,
+            dma: None,
+            dma_pending: None,
+            ppu_access_restrictions: true,
+            cgb_mode: false,
             gpu: GPU::new(),
+            timer: Timer::new(),
+            interrupt_enable: InterruptFlags::new(),
+            interrupt_flag: InterruptFlags::new(),
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    // Maps `boot_rom` over the cartridge, to run from 0x0000 at power on
+    pub fn map_boot_rom(&mut self, boot_rom: Vec<u8>) -> Result<(), BootRomError> {
+        let expected = self.model.boot_rom_size();
+        if boot_rom.len() != expected {
+            return Err(BootRomError::InvalidSize {
+                model: self.model,
+                expected,
+                actual: boot_rom.len(),
+            });
+        }
+        self.boot_rom = Some(boot_rom);
+        self.update_cgb_mode();
+        Ok(())
+    }
+
+    pub fn boot_rom_mapped(&self) -> bool {
+        self.boot_rom.is_some()
+    }
+
+    // Lets the CPU reach VRAM and OAM in any PPU mode when disabled, which
+    // helps when debugging
+    pub fn set_ppu_access_restrictions(&mut self, enabled: bool) {
+        self.ppu_access_restrictions = enabled;
+    }
+
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = Some(cartridge);
+        self.update_cgb_mode();
+    }
+
+    // Whether the CGB-only registers and banks are available
+    pub fn cgb_mode(&self) -> bool {
+        self.cgb_mode
+    }
+
+    // A CGB runs its boot ROM in CGB mode, then stays in it only for games
+    // whose header declares CGB support; others get DMG compatibility mode
+    fn update_cgb_mode(&mut self) {
+        let cgb_game = self
+            .cartridge
+            .as_ref()
+            .is_some_and(|c| c.header.cgb_support != CgbSupport::None);
+        self.cgb_mode = self.model == Model::CGB && (self.boot_rom_mapped() || cgb_game);
+        if !self.cgb_mode {
+            self.working_ram_bank = 0;
+        }
+        self.gpu.set_cgb_mode(self.cgb_mode);
+    }
+
+    // Index into `working_ram` of 0xC000 + `offset`
+    fn working_ram_index(&self, offset: usize) -> usize {
+        if offset < WORKING_RAM_BANK_SIZE {
+            return offset;
+        }
+        // Bank 0 can't be mapped twice, so selecting it gives bank 1
+        let bank = (self.working_ram_bank as usize).max(1);
+        bank * WORKING_RAM_BANK_SIZE + offset - WORKING_RAM_BANK_SIZE
+    }
+
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_ref()
+    }
+
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    // Advances everything clocked alongside the CPU by `cycles` machine cycles
+    pub fn step(&mut self, cycles: u8) {
+        for _ in 0..cycles {
+            self.step_dma();
+        }
+        self.timer.step(cycles, &mut self.interrupt_flag);
+        self.gpu.step(cycles, &mut self.interrupt_flag);
+        if let Some(cartridge) = &mut self.cartridge {
+            cartridge.step(cycles as u32);
+        }
+    }
+
+    fn step_dma(&mut self) {
+        // A restarted transfer takes over once its start delay has passed; the
+        // old one keeps running (and blocking the bus) until then
+        if let Some((source, delay)) = self.dma_pending {
+            if delay == 0 {
+                self.dma = Some(DmaTransfer { source, index: 0 });
+                self.dma_pending = None;
+            } else {
+                self.dma_pending = Some((source, delay - 1));
+            }
+        }
+
+        if let Some(mut transfer) = self.dma {
+            let byte = self.read_dma_source(transfer.source + transfer.index as u16);
+            self.gpu.write_oam(transfer.index, byte);
+            transfer.index += 1;
+            self.dma = (transfer.index < OAM_SIZE).then_some(transfer);
+        }
+    }
+
+    // DMA reads the bus directly. Sources above 0xDFFF see work RAM, as
+    // the echo RAM wiring extends over the whole top of the address space.
+    fn read_dma_source(&self, addr: u16) -> u8 {
+        if addr as usize >= ECHO_RAM_BEGIN {
+            self.read_mapped(addr - (ECHO_RAM_BEGIN - WORKING_RAM_BEGIN) as u16)
+        } else {
+            self.read_mapped(addr)
+        }
+    }
+
+    pub fn dma_active(&self) -> bool {
+        self.dma.is_some()
+    }
+
+    // While DMA runs the CPU can only reach HRAM (plus the I/O registers and
+    // IE, which aren't on the conflicting bus)
+    fn blocked_by_dma(&self, addr: u16) -> bool {
+        self.dma_active() && (addr as usize) < IO_REGISTERS_BEGIN
+    }
+
+    // VRAM and OAM are on the PPU's side while it's using them
+    fn blocked_by_ppu(&self, addr: u16) -> bool {
+        if !self.ppu_access_restrictions {
+            return false;
+        }
+        match addr as usize {
+            VRAM_BEGIN..=VRAM_END => !self.gpu.vram_accessible(),
+            OAM_BEGIN..=OAM_END => !self.gpu.oam_accessible(),
+            // CGB palette RAM is in use for as long as VRAM is
+            BCPD_ADDRESS | OCPD_ADDRESS => !self.gpu.vram_accessible(),
+            _ => false,
+        }
+    }
+
+    // Called with the operand of every 16-bit INC/DEC, which the CPU puts on
+    // the address bus. Only DMG-type models have the OAM corruption bug.
+    pub fn trigger_oam_bug(&mut self, addr: u16) {
+        let in_oam_page = (OAM_BEGIN..=UNUSABLE_END).contains(&(addr as usize));
+        if self.ppu_access_restrictions && in_oam_page && self.model != Model::CGB {
+            self.gpu.corrupt_oam();
         }
     }
 
     pub fn read_byte(&self, addr: u16) -> u8 {
+        if self.blocked_by_dma(addr) || self.blocked_by_ppu(addr) {
+            return 0xFF;
+        }
+        self.read_mapped(addr)
+    }
+
+    fn read_mapped(&self, addr: u16) -> u8 {
         let addr = addr as usize;
         match addr {
+            ROM_BANK_0_BEGIN..=ROM_BANK_N_END => {
+                if let Some(byte) = self.read_boot_rom(addr) {
+                    return byte;
+                }
+                // Without a cartridge the data bus floats high
+                match &self.cartridge {
+                    Some(cartridge) => cartridge.read_rom(addr as u16),
+                    None => 0xFF,
+                }
+            }
             VRAM_BEGIN..=VRAM_END => self.gpu.read_vram(addr - VRAM_BEGIN),
-            _ => panic!("TODO: support other areas of memory"),
+            EXTERNAL_RAM_BEGIN..=EXTERNAL_RAM_END => match &self.cartridge {
+                Some(cartridge) => cartridge.read_ram(addr as u16),
+                None => 0xFF,
+            },
+            WORKING_RAM_BEGIN..=WORKING_RAM_END => {
+                self.working_ram[self.working_ram_index(addr - WORKING_RAM_BEGIN)]
+            }
+            ECHO_RAM_BEGIN..=ECHO_RAM_END => {
+                self.working_ram[self.working_ram_index(addr - ECHO_RAM_BEGIN)]
+            }
+            OAM_BEGIN..=OAM_END => self.gpu.read_oam(addr - OAM_BEGIN),
+            // The DMG returns 0 for the unusable region
+            UNUSABLE_BEGIN..=UNUSABLE_END => 0x00,
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.read(addr),
+            // Unused upper bits of IF read back as 1
+            IF_ADDRESS => u8::from(self.interrupt_flag) | 0b1110_0000,
+            DMA_ADDRESS => self.dma_register,
+            LCDC_ADDRESS..=LYC_ADDRESS
+            | BGP_ADDRESS..=OBP1_ADDRESS
+            | WY_ADDRESS
+            | WX_ADDRESS
+            | VBK_ADDRESS
+            | BCPS_ADDRESS..=OCPD_ADDRESS => self.gpu.read_register(addr),
+            // Only the low 3 bits exist
+            SVBK_ADDRESS if self.cgb_mode => 0b1111_1000 | self.working_ram_bank,
+            IO_REGISTERS_BEGIN..=IO_REGISTERS_END => self.read_io_register(addr),
+            HIGH_RAM_BEGIN..=HIGH_RAM_END => self.high_ram[addr - HIGH_RAM_BEGIN],
+            IE_ADDRESS => u8::from(self.interrupt_enable),
+            _ => unreachable!(),
         }
     }
 
     pub fn write_byte(&mut self, addr: u16, byte: u8) {
+        if self.blocked_by_dma(addr) || self.blocked_by_ppu(addr) {
+            return;
+        }
         let addr = addr as usize;
         match addr {
+            ROM_BANK_0_BEGIN..=ROM_BANK_N_END => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.write_rom(addr as u16, byte);
+                }
+            }
             VRAM_BEGIN..=VRAM_END => self.gpu.write_vram(addr - VRAM_BEGIN, byte),
-            _ => panic!("TODO: support other areas of memory"),
+            EXTERNAL_RAM_BEGIN..=EXTERNAL_RAM_END => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.write_ram(addr as u16, byte);
+                }
+            }
+            WORKING_RAM_BEGIN..=WORKING_RAM_END => {
+                let idx = self.working_ram_index(addr - WORKING_RAM_BEGIN);
+                self.working_ram[idx] = byte
+            }
+            ECHO_RAM_BEGIN..=ECHO_RAM_END => {
+                let idx = self.working_ram_index(addr - ECHO_RAM_BEGIN);
+                self.working_ram[idx] = byte
+            }
+            OAM_BEGIN..=OAM_END => self.gpu.write_oam(addr - OAM_BEGIN, byte),
+            UNUSABLE_BEGIN..=UNUSABLE_END => {}
+            DIV_ADDRESS..=TAC_ADDRESS => self.timer.write(addr, byte),
+            IF_ADDRESS => self.interrupt_flag = InterruptFlags::from(byte),
+            DMA_ADDRESS => {
+                self.dma_register = byte;
+                self.dma_pending = Some(((byte as u16) << 8, DMA_START_DELAY));
+            }
+            LCDC_ADDRESS..=LYC_ADDRESS
+            | BGP_ADDRESS..=OBP1_ADDRESS
+            | WY_ADDRESS
+            | WX_ADDRESS
+            | VBK_ADDRESS
+            | BCPS_ADDRESS..=OCPD_ADDRESS => {
+                self.gpu
+                    .write_register(addr, byte, &mut self.interrupt_flag)
+            }
+            SVBK_ADDRESS if self.cgb_mode => self.working_ram_bank = byte & 0b111,
+            BOOT_ROM_DISABLE_ADDRESS => {
+                if byte != 0 {
+                    self.boot_rom = None;
+                    self.update_cgb_mode();
+                }
+            }
+            IO_REGISTERS_BEGIN..=IO_REGISTERS_END => self.write_io_register(addr, byte),
+            HIGH_RAM_BEGIN..=HIGH_RAM_END => self.high_ram[addr - HIGH_RAM_BEGIN] = byte,
+            IE_ADDRESS => self.interrupt_enable = InterruptFlags::from(byte),
+            _ => unreachable!(),
+        }
+    }
+
+    // Boot ROM byte covering `addr`, if any. The CGB boot ROM leaves the
+    // cartridge header at 0x0100-0x01FF visible.
+    fn read_boot_rom(&self, addr: usize) -> Option<u8> {
+        let boot_rom = self.boot_rom.as_ref()?;
+        match addr {
+            0x0100..=0x01FF => None,
+            _ => boot_rom.get(addr).copied(),
         }
     }
+
+    // I/O registers without a dedicated subsystem are plain storage; unmapped
+    // ones read as 0xFF (open bus) and ignore writes
+    fn read_io_register(&self, addr: usize) -> u8 {
+        if is_unmapped_io_register(addr) {
+            0xFF
+        } else {
+            self.io_registers[addr - IO_REGISTERS_BEGIN]
+        }
+    }
+
+    fn write_io_register(&mut self, addr: usize, byte: u8) {
+        if !is_unmapped_io_register(addr) {
+            self.io_registers[addr - IO_REGISTERS_BEGIN] = byte;
+        }
+    }
+
+    // Highest priority interrupt that is both enabled and requested
+    pub fn pending_interrupt(&self) -> Option<Interrupt> {
+        self.interrupt_flag.pending(self.interrupt_enable)
+    }
+}
+
+impl Default for MemoryBus {
+    fn default() -> Self {
+        Self::new(Model::DMG)
+    }
+}
+
+// Addresses in the I/O range that have no register behind them on the DMG
+fn is_unmapped_io_register(addr: usize) -> bool {
+    matches!(
+        addr,
+        0xFF03 | 0xFF08..=0xFF0E | 0xFF15 | 0xFF1F | 0xFF27..=0xFF2F | 0xFF4C..=0xFF7F
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::header::header_checksum;
+    use crate::gpu::Mode;
+
+    // `model` with a 32 KiB ROM of zeros, apart from the CGB flag
+    fn bus_for(model: Model, cgb_flag: u8) -> MemoryBus {
+        let mut rom = vec![0; 0x8000];
+        rom[0x143] = cgb_flag;
+        rom[0x14D] = header_checksum(&rom);
+        let mut bus = MemoryBus::new(model);
+        bus.insert_cartridge(Cartridge::from_bytes(rom).unwrap());
+        bus
+    }
+
+    fn bus() -> MemoryBus {
+        bus_for(Model::DMG, 0x00)
+    }
+
+    // 0xC000-0xC09F holds `byte ^ 0x5A` and 0xD000-0xD09F holds 0x11
+    fn bus_with_dma_sources() -> MemoryBus {
+        let mut bus = bus();
+        for idx in 0..OAM_SIZE as u16 {
+            bus.write_byte(0xC000 + idx, idx as u8 ^ 0x5A);
+            bus.write_byte(0xD000 + idx, 0x11);
+        }
+        bus
+    }
+
+    #[test]
+    fn dma_copies_160_bytes_alongside_the_cpu() {
+        let mut bus = bus_with_dma_sources();
+        bus.write_byte(DMA_ADDRESS as u16, 0xC0);
+        assert_eq!(bus.read_byte(DMA_ADDRESS as u16), 0xC0);
+        bus.step(DMA_START_DELAY);
+        assert!(!bus.dma_active());
+
+        // One byte per cycle, freeing the bus as the last one is copied
+        let mut cycles = 0;
+        loop {
+            bus.step(1);
+            cycles += 1;
+            if !bus.dma_active() {
+                break;
+            }
+        }
+        assert_eq!(cycles, OAM_SIZE);
+        for idx in 0..OAM_SIZE {
+            assert_eq!(bus.read_byte((OAM_BEGIN + idx) as u16), idx as u8 ^ 0x5A);
+        }
+    }
+
+    #[test]
+    fn dma_leaves_the_cpu_only_hram() {
+        let mut bus = bus_with_dma_sources();
+        bus.write_byte(HIGH_RAM_BEGIN as u16, 0x77);
+        bus.write_byte(DMA_ADDRESS as u16, 0xC0);
+        bus.step(DMA_START_DELAY + 1);
+
+        assert_eq!(bus.read_byte(0x0000), 0xFF);
+        assert_eq!(bus.read_byte(0xC000), 0xFF);
+        assert_eq!(bus.read_byte(HIGH_RAM_BEGIN as u16), 0x77);
+        bus.write_byte(0xC000, 0x00);
+        bus.write_byte(HIGH_RAM_BEGIN as u16, 0x78);
+
+        bus.step(OAM_SIZE as u8);
+        assert_eq!(bus.read_byte(0x0000), 0x00);
+        assert_eq!(bus.read_byte(0xC000), 0x5A);
+        assert_eq!(bus.read_byte(HIGH_RAM_BEGIN as u16), 0x78);
+    }
+
+    #[test]
+    fn restarted_dma_takes_over_after_the_start_delay() {
+        let mut bus = bus_with_dma_sources();
+        bus.write_byte(DMA_ADDRESS as u16, 0xC0);
+        bus.step(DMA_START_DELAY + 80);
+
+        // The old transfer keeps blocking the bus until the new one starts
+        bus.write_byte(DMA_ADDRESS as u16, 0xD0);
+        for _ in 0..DMA_START_DELAY {
+            bus.step(1);
+            assert!(bus.dma_active());
+            assert_eq!(bus.read_byte(0xC000), 0xFF);
+        }
+        for _ in 0..OAM_SIZE {
+            bus.step(1);
+        }
+        assert!(!bus.dma_active());
+        for idx in 0..OAM_SIZE {
+            assert_eq!(bus.read_byte((OAM_BEGIN + idx) as u16), 0x11);
+        }
+    }
+
+    // Turns the LCD on and runs until the PPU enters `mode`
+    fn run_to_mode(bus: &mut MemoryBus, mode: Mode) {
+        bus.write_byte(LCDC_ADDRESS as u16, 0x91);
+        while bus.gpu.mode() != mode {
+            bus.step(1);
+        }
+    }
+
+    #[test]
+    fn vram_is_blocked_during_pixel_transfer() {
+        let mut bus = bus();
+        bus.write_byte(0x8000, 0x12);
+        run_to_mode(&mut bus, Mode::PixelTransfer);
+        assert_eq!(bus.read_byte(0x8000), 0xFF);
+        bus.write_byte(0x8000, 0x34);
+
+        run_to_mode(&mut bus, Mode::HBlank);
+        assert_eq!(bus.read_byte(0x8000), 0x12);
+    }
+
+    #[test]
+    fn oam_is_blocked_during_oam_scan_and_pixel_transfer() {
+        let mut bus = bus();
+        bus.write_byte(OAM_BEGIN as u16, 0x12);
+        run_to_mode(&mut bus, Mode::OAMScan);
+        assert_eq!(bus.read_byte(OAM_BEGIN as u16), 0xFF);
+        bus.write_byte(OAM_BEGIN as u16, 0x34);
+        // VRAM is still free while OAM is scanned
+        assert_eq!(bus.read_byte(0x8000), 0x00);
+
+        run_to_mode(&mut bus, Mode::PixelTransfer);
+        assert_eq!(bus.read_byte(OAM_BEGIN as u16), 0xFF);
+        bus.write_byte(OAM_BEGIN as u16, 0x34);
+
+        run_to_mode(&mut bus, Mode::HBlank);
+        assert_eq!(bus.read_byte(OAM_BEGIN as u16), 0x12);
+    }
+
+    #[test]
+    fn disabled_restrictions_open_vram_and_oam() {
+        let mut bus = bus();
+        bus.set_ppu_access_restrictions(false);
+        run_to_mode(&mut bus, Mode::PixelTransfer);
+        bus.write_byte(0x8000, 0x12);
+        bus.write_byte(OAM_BEGIN as u16, 0x34);
+        assert_eq!(bus.read_byte(0x8000), 0x12);
+        assert_eq!(bus.read_byte(OAM_BEGIN as u16), 0x34);
+    }
+
+    // Fills OAM with its own indices, then triggers the OAM bug a few rows
+    // into OAM scan, returning whether OAM changed
+    fn oam_bug_corrupts(bus: &mut MemoryBus, addr: u16) -> bool {
+        for idx in 0..OAM_SIZE {
+            bus.write_byte((OAM_BEGIN + idx) as u16, idx as u8);
+        }
+        run_to_mode(bus, Mode::OAMScan);
+        bus.step(4);
+        assert_eq!(bus.gpu.mode(), Mode::OAMScan);
+        bus.trigger_oam_bug(addr);
+        (0..OAM_SIZE).any(|idx| bus.gpu.read_oam(idx) != idx as u8)
+    }
+
+    #[test]
+    fn oam_bug_only_affects_dmg_models() {
+        assert!(oam_bug_corrupts(&mut bus(), OAM_BEGIN as u16));
+        assert!(oam_bug_corrupts(&mut bus(), UNUSABLE_END as u16));
+        assert!(!oam_bug_corrupts(&mut bus(), 0xFF00));
+        assert!(!oam_bug_corrupts(
+            &mut bus_for(Model::CGB, 0x00),
+            OAM_BEGIN as u16
+        ));
+
+        let mut bus = bus();
+        bus.set_ppu_access_restrictions(false);
+        assert!(!oam_bug_corrupts(&mut bus, OAM_BEGIN as u16));
+    }
+
+    // Writes `bank` to SVBK, then `byte` to the start of 0xD000-0xDFFF
+    fn write_banked(bus: &mut MemoryBus, bank: u8, byte: u8) {
+        bus.write_byte(SVBK_ADDRESS as u16, bank);
+        bus.write_byte(0xD000, byte);
+    }
+
+    #[test]
+    fn svbk_switches_the_upper_working_ram_bank() {
+        let mut bus = bus_for(Model::CGB, 0x80);
+        bus.write_byte(0xC000, 0xC0);
+        for bank in 1..=7 {
+            write_banked(&mut bus, bank, bank * 0x11);
+        }
+        for bank in 1..=7 {
+            bus.write_byte(SVBK_ADDRESS as u16, bank);
+            assert_eq!(bus.read_byte(SVBK_ADDRESS as u16), 0b1111_1000 | bank);
+            assert_eq!(bus.read_byte(0xD000), bank * 0x11);
+            // Echo RAM follows the same bank
+            assert_eq!(bus.read_byte(0xF000), bank * 0x11);
+            assert_eq!(bus.read_byte(0xC000), 0xC0);
+        }
+
+        // Bank 0 selects bank 1, and only the low 3 bits count
+        bus.write_byte(SVBK_ADDRESS as u16, 0x00);
+        assert_eq!(bus.read_byte(SVBK_ADDRESS as u16), 0b1111_1000);
+        assert_eq!(bus.read_byte(0xD000), 0x11);
+        bus.write_byte(SVBK_ADDRESS as u16, 0b1111_1010);
+        assert_eq!(bus.read_byte(0xD000), 0x22);
+    }
+
+    #[test]
+    fn svbk_only_exists_in_cgb_mode() {
+        for model in [Model::DMG, Model::CGB] {
+            let mut bus = bus_for(model, 0x00);
+            write_banked(&mut bus, 1, 0x11);
+            write_banked(&mut bus, 2, 0x22);
+            assert_eq!(bus.read_byte(SVBK_ADDRESS as u16), 0xFF);
+            assert_eq!(bus.read_byte(0xD000), 0x22);
+        }
+
+        // The boot ROM runs in CGB mode, and unmapping it for a DMG game
+        // resets the bank
+        let mut bus = bus_for(Model::CGB, 0x00);
+        let boot_rom = vec![0; Model::CGB.boot_rom_size()];
+        bus.map_boot_rom(boot_rom).unwrap();
+        write_banked(&mut bus, 1, 0x11);
+        write_banked(&mut bus, 3, 0x33);
+        bus.write_byte(BOOT_ROM_DISABLE_ADDRESS as u16, 0x01);
+        assert!(!bus.cgb_mode());
+        assert_eq!(bus.read_byte(0xD000), 0x11);
+    }
 }
@@ -4,154 +4,394 @@ pub mod memory_bus;
 pub mod registers;
 pub mod targets;
 
+use crate::cartridge::Cartridge;
+use crate::gpu::fifo::Renderer;
+use crate::model::{BootRomError, Model};
+
 use self::instruction::Instruction;
 use self::memory_bus::MemoryBus;
 use self::registers::Registers;
 use self::targets::{
-    ADDHLTarget, ArithmeticTarget, IncDecTarget, JumpTest, LoadByteSource, LoadByteTarget,
-    LoadType, StackTarget,
+    ADDHLTarget, ArithmeticTarget, IncDecTarget, Indirect, JumpTest, LoadByteSource,
+    LoadByteTarget, LoadType, LoadWordTarget, PrefixTarget, StackTarget,
 };
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-struct CPU {
+#[derive(Debug)]
+pub struct CPU {
     pub registers: Registers,
     pc: u16, // program counter
     sp: u16, // stack pointer
     bus: MemoryBus,
     is_halted: bool,
+    halt_bug: bool,        // next opcode fetch doesn't increment PC
+    ime: bool,             // interrupt master enable
+    ime_scheduled: bool,   // EI enables interrupts after the following instruction
+    pub total_cycles: u64, // machine cycles executed since power on
 }
 
 #[allow(dead_code)]
 impl CPU {
-    pub fn step(&mut self) {
+    // Powers on `model` in the state its boot ROM hands over to the cartridge
+    pub fn new(model: Model, cartridge: Option<Cartridge>) -> Self {
+        let registers = model.post_boot_registers(cartridge.as_ref());
+        let mut bus = MemoryBus::new(model);
+        if let Some(cartridge) = cartridge {
+            bus.insert_cartridge(cartridge);
+        }
+        for (addr, value) in model.post_boot_io_registers() {
+            bus.write_byte(addr, value);
+        }
+        bus.timer.set_counter(model.post_boot_timer_counter());
+        CPU::with_bus(registers, 0x0100, 0xFFFE, bus)
+    }
+
+    // Powers on `model` running `boot_rom` from 0x0000. It stays mapped over
+    // the cartridge until it writes to 0xFF50.
+    pub fn with_boot_rom(
+        model: Model,
+        boot_rom: Vec<u8>,
+        cartridge: Option<Cartridge>,
+    ) -> Result<Self, BootRomError> {
+        let mut bus = MemoryBus::new(model);
+        bus.map_boot_rom(boot_rom)?;
+        if let Some(cartridge) = cartridge {
+            bus.insert_cartridge(cartridge);
+        }
+        Ok(CPU::with_bus(Registers::new(), 0x0000, 0x0000, bus))
+    }
+
+    fn with_bus(registers: Registers, pc: u16, sp: u16, bus: MemoryBus) -> Self {
+        CPU {
+            registers,
+            pc,
+            sp,
+            bus,
+            is_halted: false,
+            halt_bug: false,
+            ime: false,
+            ime_scheduled: false,
+            total_cycles: 0,
+        }
+    }
+
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.bus.cartridge()
+    }
+
+    // For saving, and for feeding the cartridge's sensors from the host
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.bus.cartridge_mut()
+    }
+
+    // The last frame drawn, row by row, as RGB555
+    pub fn framebuffer(&self) -> &[u16] {
+        self.bus.gpu.framebuffer()
+    }
+
+    // Takes effect from the next line
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.bus.gpu.set_renderer(renderer);
+    }
+
+    // Disabling lets a debugger poke VRAM and OAM during rendering
+    pub fn set_ppu_access_restrictions(&mut self, enabled: bool) {
+        self.bus.set_ppu_access_restrictions(enabled);
+    }
+
+    // Runs a single instruction (or interrupt dispatch) and returns the number of
+    // machine cycles it took
+    pub fn step(&mut self) -> u8 {
+        let cycles = match self.handle_interrupts() {
+            Some(cycles) => cycles,
+            // A halted CPU still lets time pass for the rest of the system
+            None if self.is_halted => 1,
+            None => self.step_instruction(),
+        };
+
+        self.bus.step(cycles);
+        self.total_cycles += cycles as u64;
+        cycles
+    }
+
+    fn step_instruction(&mut self) -> u8 {
+        let enable_ime = self.ime_scheduled;
+
         let mut instruction_byte = self.bus.read_byte(self.pc);
+        if self.halt_bug {
+            // The opcode byte is read again as the start of the next instruction
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
         let prefixed = instruction_byte == 0xCB;
         if prefixed {
-            instruction_byte = self.bus.read_byte(self.pc + 1);
+            instruction_byte = self.bus.read_byte(self.pc.wrapping_add(1));
+        }
+
+        let instruction = if prefixed {
+            Some(Instruction::from_prefixed_byte(instruction_byte))
+        } else {
+            Instruction::from_byte(instruction_byte)
+        };
+
+        let cycles = match instruction {
+            Some(instruction) => {
+                // Flags are checked before execution, as the branch itself may change them
+                let branch_taken = match instruction {
+                    Instruction::JP(test)
+                    | Instruction::JR(test)
+                    | Instruction::CALL(test)
+                    | Instruction::RET(test) => self.should_jump(test),
+                    _ => false,
+                };
+                self.pc = self.execute(instruction);
+                instruction.cycles(branch_taken)
+            }
+            None => 1,
+        };
+
+        // EI takes effect once the instruction after it has run, unless DI cancelled it
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
         }
 
-        if let Some(instruction) = Instruction::from_byte(instruction_byte) {
-            let next_pc = self.execute(instruction);
-            self.pc = next_pc;
+        cycles
+    }
+
+    // Wakes the CPU from HALT on any pending interrupt and, if IME is set,
+    // dispatches the highest priority one. Returns the cycles spent dispatching.
+    fn handle_interrupts(&mut self) -> Option<u8> {
+        let interrupt = self.bus.pending_interrupt()?;
+        self.is_halted = false;
+        if !self.ime {
+            return None;
         }
+
+        self.ime = false;
+        self.bus.interrupt_flag.clear(interrupt);
+        self.push(self.pc);
+        self.pc = interrupt.vector();
+        Some(5)
     }
 
     pub fn execute(&mut self, instruction: Instruction) -> u16 {
-        if self.is_halted {
-            return self.pc;
-        }
+        let next_pc = self.pc.wrapping_add(instruction.length());
         match instruction {
             Instruction::INC(register) => match register {
                 IncDecTarget::A => {
-                    self.registers.a = self.registers.a.wrapping_add(1);
-                    self.pc
+                    self.registers.a = self.inc(self.registers.a);
+                    next_pc
                 }
                 IncDecTarget::B => {
-                    self.registers.b = self.registers.b.wrapping_add(1);
-                    self.pc
+                    self.registers.b = self.inc(self.registers.b);
+                    next_pc
                 }
                 IncDecTarget::C => {
-                    self.registers.c = self.registers.c.wrapping_add(1);
-                    self.pc
+                    self.registers.c = self.inc(self.registers.c);
+                    next_pc
                 }
                 IncDecTarget::D => {
-                    self.registers.d = self.registers.d.wrapping_add(1);
-                    self.pc
+                    self.registers.d = self.inc(self.registers.d);
+                    next_pc
                 }
                 IncDecTarget::E => {
-                    self.registers.e = self.registers.e.wrapping_add(1);
-                    self.pc
+                    self.registers.e = self.inc(self.registers.e);
+                    next_pc
                 }
                 IncDecTarget::H => {
-                    self.registers.h = self.registers.h.wrapping_add(1);
-                    self.pc
+                    self.registers.h = self.inc(self.registers.h);
+                    next_pc
                 }
                 IncDecTarget::L => {
-                    self.registers.l = self.registers.l.wrapping_add(1);
-                    self.pc
+                    self.registers.l = self.inc(self.registers.l);
+                    next_pc
+                }
+                IncDecTarget::HLI => {
+                    let addr = self.registers.get_hl();
+                    let value = self.bus.read_byte(addr);
+                    let res = self.inc(value);
+                    self.bus.write_byte(addr, res);
+                    next_pc
                 }
                 IncDecTarget::BC => {
                     let value = self.registers.get_bc();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_add(1);
                     self.registers.set_bc(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::DE => {
                     let value = self.registers.get_de();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_add(1);
                     self.registers.set_de(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::HL => {
                     let value = self.registers.get_hl();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_add(1);
                     self.registers.set_hl(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::SP => {
+                    self.bus.trigger_oam_bug(self.sp);
                     self.sp = self.sp.wrapping_add(1);
-                    self.pc
+                    next_pc
                 }
             },
             Instruction::DEC(register) => match register {
                 IncDecTarget::A => {
-                    self.registers.a = self.registers.a.wrapping_sub(1);
-                    self.pc
+                    self.registers.a = self.dec(self.registers.a);
+                    next_pc
                 }
                 IncDecTarget::B => {
-                    self.registers.b = self.registers.b.wrapping_sub(1);
-                    self.pc
+                    self.registers.b = self.dec(self.registers.b);
+                    next_pc
                 }
                 IncDecTarget::C => {
-                    self.registers.c = self.registers.c.wrapping_sub(1);
-                    self.pc
+                    self.registers.c = self.dec(self.registers.c);
+                    next_pc
                 }
                 IncDecTarget::D => {
-                    self.registers.d = self.registers.d.wrapping_sub(1);
-                    self.pc
+                    self.registers.d = self.dec(self.registers.d);
+                    next_pc
                 }
                 IncDecTarget::E => {
-                    self.registers.e = self.registers.e.wrapping_sub(1);
-                    self.pc
+                    self.registers.e = self.dec(self.registers.e);
+                    next_pc
                 }
                 IncDecTarget::H => {
-                    self.registers.h = self.registers.h.wrapping_sub(1);
-                    self.pc
+                    self.registers.h = self.dec(self.registers.h);
+                    next_pc
                 }
                 IncDecTarget::L => {
-                    self.registers.l = self.registers.l.wrapping_sub(1);
-                    self.pc
+                    self.registers.l = self.dec(self.registers.l);
+                    next_pc
+                }
+                IncDecTarget::HLI => {
+                    let addr = self.registers.get_hl();
+                    let value = self.bus.read_byte(addr);
+                    let res = self.dec(value);
+                    self.bus.write_byte(addr, res);
+                    next_pc
                 }
                 IncDecTarget::BC => {
                     let value = self.registers.get_bc();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_sub(1);
                     self.registers.set_bc(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::DE => {
                     let value = self.registers.get_de();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_sub(1);
                     self.registers.set_de(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::HL => {
                     let value = self.registers.get_hl();
+                    self.bus.trigger_oam_bug(value);
                     let new_value = value.wrapping_sub(1);
                     self.registers.set_hl(new_value);
-                    self.pc
+                    next_pc
                 }
                 IncDecTarget::SP => {
+                    self.bus.trigger_oam_bug(self.sp);
                     self.sp = self.sp.wrapping_sub(1);
-                    self.pc
+                    next_pc
                 }
             },
-            Instruction::ADD(register) => match register {
-                ArithmeticTarget::C => {
-                    self.registers.a = self.add(self.registers.c);
-                    self.pc
-                }
-                _ => self.pc,
-            },
+            Instruction::ADD(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.add(value);
+                next_pc
+            }
+            Instruction::ADC(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.adc(value);
+                next_pc
+            }
+            Instruction::SUB(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.sub(value);
+                next_pc
+            }
+            Instruction::SBC(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.sbc(value);
+                next_pc
+            }
+            Instruction::AND(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.and(value);
+                next_pc
+            }
+            Instruction::OR(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.or(value);
+                next_pc
+            }
+            Instruction::XOR(target) => {
+                let value = self.read_arithmetic_target(target);
+                self.registers.a = self.xor(value);
+                next_pc
+            }
+            Instruction::CP(target) => {
+                // Compare is a subtraction that only keeps the flags
+                let value = self.read_arithmetic_target(target);
+                self.sub(value);
+                next_pc
+            }
+            Instruction::ADDSP => {
+                self.sp = self.add_sp_signed();
+                next_pc
+            }
+            Instruction::DAA => {
+                self.registers.a = self.daa();
+                next_pc
+            }
+            Instruction::CPL => {
+                self.registers.a = !self.registers.a;
+                self.registers.f.subtract = true;
+                self.registers.f.half_carry = true;
+                next_pc
+            }
+            Instruction::SCF => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = true;
+                next_pc
+            }
+            Instruction::CCF => {
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = false;
+                self.registers.f.carry = !self.registers.f.carry;
+                next_pc
+            }
+            // The accumulator rotates behave like their prefixed versions but
+            // always clear the zero flag
+            Instruction::RLCA => {
+                self.registers.a = self.rlc(self.registers.a);
+                self.registers.f.zero = false;
+                next_pc
+            }
+            Instruction::RRCA => {
+                self.registers.a = self.rrc(self.registers.a);
+                self.registers.f.zero = false;
+                next_pc
+            }
+            Instruction::RLA => {
+                self.registers.a = self.rl(self.registers.a);
+                self.registers.f.zero = false;
+                next_pc
+            }
+            Instruction::RRA => {
+                self.registers.a = self.rr(self.registers.a);
+                self.registers.f.zero = false;
+                next_pc
+            }
             Instruction::ADDHL(register) => {
                 let value = match register {
                     ADDHLTarget::BC => self.registers.get_bc(),
@@ -161,7 +401,7 @@ impl CPU {
                 };
                 let res = self.add_hl(value);
                 self.registers.set_hl(res);
-                self.pc
+                next_pc
             }
             Instruction::JP(test) => {
                 let jump_condition = self.should_jump(test);
@@ -171,21 +411,74 @@ impl CPU {
                 LoadType::Byte(target, source) => {
                     let source_value = match source {
                         LoadByteSource::A => self.registers.a,
+                        LoadByteSource::B => self.registers.b,
+                        LoadByteSource::C => self.registers.c,
+                        LoadByteSource::D => self.registers.d,
+                        LoadByteSource::E => self.registers.e,
+                        LoadByteSource::H => self.registers.h,
+                        LoadByteSource::L => self.registers.l,
                         LoadByteSource::D8 => self.read_next_byte(),
                         LoadByteSource::HLI => self.bus.read_byte(self.registers.get_hl()),
-                        _ => panic!("TODO: implement other sources"),
                     };
                     match target {
                         LoadByteTarget::A => self.registers.a = source_value,
+                        LoadByteTarget::B => self.registers.b = source_value,
+                        LoadByteTarget::C => self.registers.c = source_value,
+                        LoadByteTarget::D => self.registers.d = source_value,
+                        LoadByteTarget::E => self.registers.e = source_value,
+                        LoadByteTarget::H => self.registers.h = source_value,
+                        LoadByteTarget::L => self.registers.l = source_value,
                         LoadByteTarget::HLI => {
                             self.bus.write_byte(self.registers.get_hl(), source_value)
                         }
-                        _ => panic!("TODO: implement other targets"),
                     };
-                    match source {
-                        LoadByteSource::D8 => self.pc.wrapping_add(2),
-                        _ => self.pc.wrapping_add(1),
-                    }
+                    next_pc
+                }
+                LoadType::Word(target) => {
+                    let word = self.read_next_word();
+                    match target {
+                        LoadWordTarget::BC => self.registers.set_bc(word),
+                        LoadWordTarget::DE => self.registers.set_de(word),
+                        LoadWordTarget::HL => self.registers.set_hl(word),
+                        LoadWordTarget::SP => self.sp = word,
+                    };
+                    next_pc
+                }
+                LoadType::AFromIndirect(source) => {
+                    let addr = self.indirect_address(source);
+                    self.registers.a = self.bus.read_byte(addr);
+                    next_pc
+                }
+                LoadType::IndirectFromA(target) => {
+                    let addr = self.indirect_address(target);
+                    self.bus.write_byte(addr, self.registers.a);
+                    next_pc
+                }
+                LoadType::AFromByteAddress => {
+                    let addr = 0xFF00 | self.read_next_byte() as u16;
+                    self.registers.a = self.bus.read_byte(addr);
+                    next_pc
+                }
+                LoadType::ByteAddressFromA => {
+                    let addr = 0xFF00 | self.read_next_byte() as u16;
+                    self.bus.write_byte(addr, self.registers.a);
+                    next_pc
+                }
+                LoadType::IndirectFromSP => {
+                    let addr = self.read_next_word();
+                    self.bus.write_byte(addr, (self.sp & 0xFF) as u8);
+                    self.bus
+                        .write_byte(addr.wrapping_add(1), (self.sp >> 8) as u8);
+                    next_pc
+                }
+                LoadType::SPFromHL => {
+                    self.sp = self.registers.get_hl();
+                    next_pc
+                }
+                LoadType::HLFromSPN => {
+                    let value = self.add_sp_signed();
+                    self.registers.set_hl(value);
+                    next_pc
                 }
             },
             Instruction::PUSH(target) => {
@@ -196,7 +489,7 @@ impl CPU {
                     StackTarget::AF => self.registers.get_af(),
                 };
                 self.push(value);
-                self.pc.wrapping_add(1)
+                next_pc
             }
             Instruction::POP(target) => {
                 let result = self.pop();
@@ -206,7 +499,7 @@ impl CPU {
                     StackTarget::HL => self.registers.set_hl(result),
                     StackTarget::AF => self.registers.set_af(result),
                 };
-                self.pc.wrapping_add(1)
+                next_pc
             }
             Instruction::CALL(test) => {
                 let jump_condition = self.should_jump(test);
@@ -216,29 +509,94 @@ impl CPU {
                 let jump_condition = self.should_jump(test);
                 self.return_(jump_condition)
             }
-            Instruction::NOP => self.pc.wrapping_add(1),
+            Instruction::RLC(target) => self.prefixed_op(target, Self::rlc, next_pc),
+            Instruction::RRC(target) => self.prefixed_op(target, Self::rrc, next_pc),
+            Instruction::RL(target) => self.prefixed_op(target, Self::rl, next_pc),
+            Instruction::RR(target) => self.prefixed_op(target, Self::rr, next_pc),
+            Instruction::SLA(target) => self.prefixed_op(target, Self::sla, next_pc),
+            Instruction::SRA(target) => self.prefixed_op(target, Self::sra, next_pc),
+            Instruction::SWAP(target) => self.prefixed_op(target, Self::swap, next_pc),
+            Instruction::SRL(target) => self.prefixed_op(target, Self::srl, next_pc),
+            Instruction::BIT(bit, target) => {
+                let value = self.read_prefix_target(target);
+                self.registers.f.zero = value & (1 << bit) == 0;
+                self.registers.f.subtract = false;
+                self.registers.f.half_carry = true;
+                next_pc
+            }
+            Instruction::RESET(bit, target) => {
+                let value = self.read_prefix_target(target);
+                self.write_prefix_target(target, value & !(1 << bit));
+                next_pc
+            }
+            Instruction::SET(bit, target) => {
+                let value = self.read_prefix_target(target);
+                self.write_prefix_target(target, value | (1 << bit));
+                next_pc
+            }
+            Instruction::NOP => next_pc,
+            Instruction::JPHL => self.registers.get_hl(),
+            Instruction::JR(test) => {
+                let jump_condition = self.should_jump(test);
+                self.jump_relative(jump_condition, next_pc)
+            }
+            Instruction::RST(vector) => {
+                self.push(next_pc);
+                vector as u16
+            }
+            Instruction::RETI => {
+                // Unlike EI, RETI enables interrupts immediately
+                self.ime = true;
+                self.pop()
+            }
             Instruction::HALT => {
-                self.is_halted = true;
-                self.pc
+                if !self.ime && self.bus.pending_interrupt().is_some() {
+                    // HALT exits immediately and the following byte is read twice
+                    self.halt_bug = true;
+                } else {
+                    self.is_halted = true;
+                }
+                next_pc
+            }
+            Instruction::DI => {
+                self.ime = false;
+                self.ime_scheduled = false;
+                next_pc
+            }
+            Instruction::EI => {
+                self.ime_scheduled = true;
+                next_pc
             }
-            _ => self.pc,
+            Instruction::STOP => next_pc,
+            // Unused opcodes hang the CPU, so never move past them
+            Instruction::ILLEGAL(_) => self.pc,
         }
     }
 
     pub fn read_next_byte(&self) -> u8 {
-        self.bus.read_byte(self.pc + 1)
+        self.bus.read_byte(self.pc.wrapping_add(1))
     }
 
     pub fn jump(&self, should_jump: bool) -> u16 {
         if should_jump {
-            let least_significant_byte = self.bus.read_byte(self.pc + 1) as u16;
-            let most_significant_byte = self.bus.read_byte(self.pc + 2) as u16;
+            let least_significant_byte = self.bus.read_byte(self.pc.wrapping_add(1)) as u16;
+            let most_significant_byte = self.bus.read_byte(self.pc.wrapping_add(2)) as u16;
             (most_significant_byte << 8) | least_significant_byte
         } else {
             self.pc.wrapping_add(3)
         }
     }
 
+    pub fn jump_relative(&self, should_jump: bool, next_pc: u16) -> u16 {
+        if should_jump {
+            // The offset is signed and relative to the following instruction
+            let offset = self.read_next_byte() as i8;
+            next_pc.wrapping_add(offset as u16)
+        } else {
+            next_pc
+        }
+    }
+
     pub fn should_jump(&self, test: JumpTest) -> bool {
         match test {
             JumpTest::NotZero => !self.registers.f.zero,
@@ -266,12 +624,277 @@ impl CPU {
 
         self.registers.f.subtract = false;
         self.registers.f.carry = carry;
-        let mask = 0b111_1111_1111;
+        // Half carry is the carry out of bit 11
+        let mask = 0b1111_1111_1111;
         self.registers.f.half_carry = (value & mask) + (hl & mask) > mask;
 
         res
     }
 
+    pub fn adc(&mut self, value: u8) -> u8 {
+        let carry = self.registers.f.carry as u8;
+        let res = self.registers.a.wrapping_add(value).wrapping_add(carry);
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = self.registers.a as u16 + value as u16 + carry as u16 > 0xFF;
+        self.registers.f.half_carry = (self.registers.a & 0xF) + (value & 0xF) + carry > 0xF;
+
+        res
+    }
+
+    pub fn sub(&mut self, value: u8) -> u8 {
+        let (res, carry) = self.registers.a.overflowing_sub(value);
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = carry;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF);
+
+        res
+    }
+
+    pub fn sbc(&mut self, value: u8) -> u8 {
+        let carry = self.registers.f.carry as u8;
+        let res = self.registers.a.wrapping_sub(value).wrapping_sub(carry);
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.carry = (self.registers.a as u16) < value as u16 + carry as u16;
+        self.registers.f.half_carry = (self.registers.a & 0xF) < (value & 0xF) + carry;
+
+        res
+    }
+
+    pub fn and(&mut self, value: u8) -> u8 {
+        let res = self.registers.a & value;
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = true;
+
+        res
+    }
+
+    pub fn or(&mut self, value: u8) -> u8 {
+        let res = self.registers.a | value;
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = false;
+
+        res
+    }
+
+    pub fn xor(&mut self, value: u8) -> u8 {
+        let res = self.registers.a ^ value;
+
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = false;
+        self.registers.f.half_carry = false;
+
+        res
+    }
+
+    pub fn inc(&mut self, value: u8) -> u8 {
+        let res = value.wrapping_add(1);
+
+        // Carry is left untouched
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = value & 0xF == 0xF;
+
+        res
+    }
+
+    pub fn dec(&mut self, value: u8) -> u8 {
+        let res = value.wrapping_sub(1);
+
+        // Carry is left untouched
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = true;
+        self.registers.f.half_carry = value & 0xF == 0;
+
+        res
+    }
+
+    // SP + signed immediate, shared by ADD SP,e8 and LD HL,SP+e8
+    pub fn add_sp_signed(&mut self) -> u16 {
+        let value = self.read_next_byte();
+        let res = self.sp.wrapping_add(value as i8 as u16);
+
+        // Flags come from the unsigned addition of the low byte
+        self.registers.f.zero = false;
+        self.registers.f.subtract = false;
+        self.registers.f.carry = (self.sp & 0xFF) + value as u16 > 0xFF;
+        self.registers.f.half_carry = (self.sp & 0xF) + (value as u16 & 0xF) > 0xF;
+
+        res
+    }
+
+    // Adjusts A back into binary-coded decimal after an addition or subtraction
+    pub fn daa(&mut self) -> u8 {
+        let mut a = self.registers.a;
+        let mut carry = self.registers.f.carry;
+
+        if !self.registers.f.subtract {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.registers.f.half_carry || a & 0xF > 0x9 {
+                a = a.wrapping_add(0x06);
+            }
+        } else {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.registers.f.half_carry {
+                a = a.wrapping_sub(0x06);
+            }
+        }
+
+        self.registers.f.zero = a == 0;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        a
+    }
+
+    // Resolves the address of an indirect load, applying the HL post
+    // increment/decrement
+    pub fn indirect_address(&mut self, indirect: Indirect) -> u16 {
+        match indirect {
+            Indirect::BCIndirect => self.registers.get_bc(),
+            Indirect::DEIndirect => self.registers.get_de(),
+            Indirect::HLIndirectMinus => {
+                let hl = self.registers.get_hl();
+                self.registers.set_hl(hl.wrapping_sub(1));
+                hl
+            }
+            Indirect::HLIndirectPlus => {
+                let hl = self.registers.get_hl();
+                self.registers.set_hl(hl.wrapping_add(1));
+                hl
+            }
+            Indirect::WordIndirect => self.read_next_word(),
+            Indirect::LastByteIndirect => 0xFF00 | self.registers.c as u16,
+        }
+    }
+
+    pub fn read_arithmetic_target(&self, target: ArithmeticTarget) -> u8 {
+        match target {
+            ArithmeticTarget::A => self.registers.a,
+            ArithmeticTarget::B => self.registers.b,
+            ArithmeticTarget::C => self.registers.c,
+            ArithmeticTarget::D => self.registers.d,
+            ArithmeticTarget::E => self.registers.e,
+            ArithmeticTarget::H => self.registers.h,
+            ArithmeticTarget::L => self.registers.l,
+            ArithmeticTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+            ArithmeticTarget::D8 => self.read_next_byte(),
+        }
+    }
+
+    pub fn read_prefix_target(&self, target: PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::A => self.registers.a,
+            PrefixTarget::B => self.registers.b,
+            PrefixTarget::C => self.registers.c,
+            PrefixTarget::D => self.registers.d,
+            PrefixTarget::E => self.registers.e,
+            PrefixTarget::H => self.registers.h,
+            PrefixTarget::L => self.registers.l,
+            PrefixTarget::HLI => self.bus.read_byte(self.registers.get_hl()),
+        }
+    }
+
+    pub fn write_prefix_target(&mut self, target: PrefixTarget, value: u8) {
+        match target {
+            PrefixTarget::A => self.registers.a = value,
+            PrefixTarget::B => self.registers.b = value,
+            PrefixTarget::C => self.registers.c = value,
+            PrefixTarget::D => self.registers.d = value,
+            PrefixTarget::E => self.registers.e = value,
+            PrefixTarget::H => self.registers.h = value,
+            PrefixTarget::L => self.registers.l = value,
+            PrefixTarget::HLI => self.bus.write_byte(self.registers.get_hl(), value),
+        }
+    }
+
+    // Applies a read-modify-write shift/rotate to a prefix target
+    fn prefixed_op(
+        &mut self,
+        target: PrefixTarget,
+        op: fn(&mut Self, u8) -> u8,
+        next_pc: u16,
+    ) -> u16 {
+        let value = self.read_prefix_target(target);
+        let res = op(self, value);
+        self.write_prefix_target(target, res);
+        next_pc
+    }
+
+    // Sets the flags shared by every shift, rotate and swap
+    fn set_shift_flags(&mut self, res: u8, carry: bool) {
+        self.registers.f.zero = res == 0;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+    }
+
+    pub fn rlc(&mut self, value: u8) -> u8 {
+        let res = value.rotate_left(1);
+        self.set_shift_flags(res, value & 0x80 != 0);
+        res
+    }
+
+    pub fn rrc(&mut self, value: u8) -> u8 {
+        let res = value.rotate_right(1);
+        self.set_shift_flags(res, value & 0x01 != 0);
+        res
+    }
+
+    pub fn rl(&mut self, value: u8) -> u8 {
+        let res = (value << 1) | self.registers.f.carry as u8;
+        self.set_shift_flags(res, value & 0x80 != 0);
+        res
+    }
+
+    pub fn rr(&mut self, value: u8) -> u8 {
+        let res = (value >> 1) | ((self.registers.f.carry as u8) << 7);
+        self.set_shift_flags(res, value & 0x01 != 0);
+        res
+    }
+
+    pub fn sla(&mut self, value: u8) -> u8 {
+        let res = value << 1;
+        self.set_shift_flags(res, value & 0x80 != 0);
+        res
+    }
+
+    pub fn sra(&mut self, value: u8) -> u8 {
+        // Arithmetic shift keeps the sign bit
+        let res = (value >> 1) | (value & 0x80);
+        self.set_shift_flags(res, value & 0x01 != 0);
+        res
+    }
+
+    pub fn srl(&mut self, value: u8) -> u8 {
+        let res = value >> 1;
+        self.set_shift_flags(res, value & 0x01 != 0);
+        res
+    }
+
+    pub fn swap(&mut self, value: u8) -> u8 {
+        let res = value.rotate_left(4);
+        self.set_shift_flags(res, false);
+        res
+    }
+
     pub fn push(&mut self, value: u16) {
         self.sp = self.sp.wrapping_sub(1);
         self.bus.write_byte(self.sp, ((value & 0xFF00) >> 8) as u8);
@@ -292,7 +915,8 @@ impl CPU {
 
     pub fn read_next_word(&mut self) -> u16 {
         // Gameboy is little endian, so pc+2->MSB & pc+1->LSB
-        ((self.bus.read_byte(self.pc + 2) as u16) << 8) | (self.bus.read_byte(self.pc + 1) as u16)
+        ((self.bus.read_byte(self.pc.wrapping_add(2)) as u16) << 8)
+            | (self.bus.read_byte(self.pc.wrapping_add(1)) as u16)
     }
 
     pub fn call(&mut self, should_jump: bool) -> u16 {
@@ -313,3 +937,305 @@ impl CPU {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::flags_register::FlagsRegister;
+    use super::*;
+    use crate::cartridge::header::header_checksum;
+    use crate::interrupts::{Interrupt, IE_ADDRESS, IF_ADDRESS};
+
+    // DMG running `program` from 0x0150, past the cartridge header
+    fn cpu_with(program: &[u8]) -> CPU {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]); // JP 0x0150
+        rom[0x150..0x150 + program.len()].copy_from_slice(program);
+        rom[0x14D] = header_checksum(&rom);
+        let cartridge = Cartridge::from_bytes(rom).unwrap();
+        let mut cpu = CPU::new(Model::DMG, Some(cartridge));
+        cpu.step();
+        cpu
+    }
+
+    fn run(cpu: &mut CPU, instructions: usize) {
+        for _ in 0..instructions {
+            cpu.step();
+        }
+    }
+
+    // A after LD A,`a`; `opcode` `value`; DAA, with Z, H and C
+    fn daa_after(opcode: u8, a: u8, value: u8) -> (u8, bool, bool, bool) {
+        let mut cpu = cpu_with(&[0x3E, a, opcode, value, 0x27]);
+        run(&mut cpu, 3);
+        let f = cpu.registers.f;
+        (cpu.registers.a, f.zero, f.half_carry, f.carry)
+    }
+
+    #[test]
+    fn daa_after_add() {
+        const ADD: u8 = 0xC6;
+        // Low digit over 9
+        assert_eq!(daa_after(ADD, 0x45, 0x38), (0x83, false, false, false));
+        // Low digits carried into the high nibble
+        assert_eq!(daa_after(ADD, 0x09, 0x08), (0x17, false, false, false));
+        assert_eq!(daa_after(ADD, 0x99, 0x01), (0x00, true, false, true));
+        // Carry out of the byte
+        assert_eq!(daa_after(ADD, 0x90, 0x90), (0x80, false, false, true));
+        assert_eq!(daa_after(ADD, 0x99, 0x99), (0x98, false, false, true));
+    }
+
+    #[test]
+    fn daa_after_sub() {
+        const SUB: u8 = 0xD6;
+        // Borrow from the high nibble
+        assert_eq!(daa_after(SUB, 0x42, 0x05), (0x37, false, false, false));
+        assert_eq!(daa_after(SUB, 0x10, 0x20), (0x90, false, false, true));
+        assert_eq!(daa_after(SUB, 0x00, 0x01), (0x99, false, false, true));
+        assert_eq!(daa_after(SUB, 0x12, 0x12), (0x00, true, false, false));
+    }
+
+    // LD A,`a`; SCF; CCF or NOP; CB `opcode`, returning A with Z, N, H and C
+    fn prefixed_on_a(opcode: u8, a: u8, carry: bool) -> (u8, bool, bool, bool, bool) {
+        let ccf = if carry { 0x00 } else { 0x3F };
+        let mut cpu = cpu_with(&[0x3E, a, 0x37, ccf, 0xCB, opcode]);
+        run(&mut cpu, 4);
+        assert_eq!(cpu.pc, 0x0156);
+        let f = cpu.registers.f;
+        (cpu.registers.a, f.zero, f.subtract, f.half_carry, f.carry)
+    }
+
+    #[test]
+    fn prefixed_shift_and_rotate_flags() {
+        // RLC A
+        assert_eq!(
+            prefixed_on_a(0x07, 0x85, false),
+            (0x0B, false, false, false, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x07, 0x00, true),
+            (0x00, true, false, false, false)
+        );
+        // RR A rotates the old carry in
+        assert_eq!(
+            prefixed_on_a(0x1F, 0x01, false),
+            (0x00, true, false, false, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x1F, 0x02, true),
+            (0x81, false, false, false, false)
+        );
+        // SLA A
+        assert_eq!(
+            prefixed_on_a(0x27, 0x80, false),
+            (0x00, true, false, false, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x27, 0x41, true),
+            (0x82, false, false, false, false)
+        );
+        // SRA A keeps the sign bit
+        assert_eq!(
+            prefixed_on_a(0x2F, 0x81, false),
+            (0xC0, false, false, false, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x2F, 0x01, false),
+            (0x00, true, false, false, true)
+        );
+        // SWAP A always clears carry
+        assert_eq!(
+            prefixed_on_a(0x37, 0xF1, true),
+            (0x1F, false, false, false, false)
+        );
+        assert_eq!(
+            prefixed_on_a(0x37, 0x00, true),
+            (0x00, true, false, false, false)
+        );
+        // SRL A shifts in zero
+        assert_eq!(
+            prefixed_on_a(0x3F, 0x81, false),
+            (0x40, false, false, false, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x3F, 0x01, false),
+            (0x00, true, false, false, true)
+        );
+    }
+
+    #[test]
+    fn bit_sets_zero_and_half_carry_and_keeps_carry() {
+        // BIT 0,A and BIT 7,A
+        assert_eq!(
+            prefixed_on_a(0x47, 0xFE, true),
+            (0xFE, true, false, true, true)
+        );
+        assert_eq!(
+            prefixed_on_a(0x47, 0x01, false),
+            (0x01, false, false, true, false)
+        );
+        assert_eq!(
+            prefixed_on_a(0x7F, 0x80, false),
+            (0x80, false, false, true, false)
+        );
+        assert_eq!(
+            prefixed_on_a(0x7F, 0x7F, true),
+            (0x7F, true, false, true, true)
+        );
+    }
+
+    // LD HL,0xC000; LD (HL),`value`; CB `opcode`, returning (HL) and F
+    fn prefixed_on_hl(opcode: u8, value: u8) -> (u8, FlagsRegister) {
+        let mut cpu = cpu_with(&[0x21, 0x00, 0xC0, 0x36, value, 0xCB, opcode]);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.pc, 0x0157);
+        (cpu.bus.read_byte(0xC000), cpu.registers.f)
+    }
+
+    #[test]
+    fn prefixed_ops_write_back_to_hl() {
+        // SWAP (HL)
+        let (value, f) = prefixed_on_hl(0x36, 0xA5);
+        assert_eq!(value, 0x5A);
+        assert!(!f.zero && !f.carry);
+        // SRA (HL)
+        let (value, f) = prefixed_on_hl(0x2E, 0x83);
+        assert_eq!(value, 0xC1);
+        assert!(!f.zero && f.carry);
+        // BIT 7,(HL) leaves memory alone
+        let (value, f) = prefixed_on_hl(0x7E, 0x7F);
+        assert_eq!(value, 0x7F);
+        assert!(f.zero && f.half_carry);
+    }
+
+    // LD SP,`sp`; `opcode` `offset`, returning SP and HL with H and C
+    fn add_sp(opcode: u8, sp: u16, offset: u8) -> (u16, u16, bool, bool) {
+        let [low, high] = sp.to_le_bytes();
+        let mut cpu = cpu_with(&[0x31, low, high, 0x21, 0x00, 0x00, opcode, offset]);
+        run(&mut cpu, 3);
+        let f = cpu.registers.f;
+        assert!(!f.zero && !f.subtract);
+        (cpu.sp, cpu.registers.get_hl(), f.half_carry, f.carry)
+    }
+
+    #[test]
+    fn add_sp_e8_flags() {
+        const ADD_SP: u8 = 0xE8;
+        assert_eq!(add_sp(ADD_SP, 0x00FF, 0x01), (0x0100, 0, true, true));
+        assert_eq!(add_sp(ADD_SP, 0x000F, 0x01), (0x0010, 0, true, false));
+        assert_eq!(add_sp(ADD_SP, 0x00F0, 0x10), (0x0100, 0, false, true));
+        // Flags come from the low byte, even for negative offsets
+        assert_eq!(add_sp(ADD_SP, 0x1000, 0xFF), (0x0FFF, 0, false, false));
+        assert_eq!(add_sp(ADD_SP, 0x0001, 0xFF), (0x0000, 0, true, true));
+    }
+
+    #[test]
+    fn ld_hl_sp_e8_flags() {
+        const LD_HL_SP: u8 = 0xF8;
+        assert_eq!(add_sp(LD_HL_SP, 0x00FF, 0x01), (0x00FF, 0x0100, true, true));
+        assert_eq!(
+            add_sp(LD_HL_SP, 0x000F, 0x01),
+            (0x000F, 0x0010, true, false)
+        );
+        assert_eq!(
+            add_sp(LD_HL_SP, 0x00F0, 0x10),
+            (0x00F0, 0x0100, false, true)
+        );
+        assert_eq!(add_sp(LD_HL_SP, 0xFFF8, 0x08), (0xFFF8, 0x0000, true, true));
+        assert_eq!(
+            add_sp(LD_HL_SP, 0x1000, 0xFE),
+            (0x1000, 0x0FFE, false, false)
+        );
+    }
+
+    // Sets IE and IF, e.g. 0x04 for the timer
+    fn set_interrupts(cpu: &mut CPU, enabled: u8, requested: u8) {
+        cpu.bus.write_byte(IE_ADDRESS as u16, enabled);
+        cpu.bus.write_byte(IF_ADDRESS as u16, requested);
+    }
+
+    #[test]
+    fn ei_takes_effect_after_the_next_instruction() {
+        // EI; NOP; NOP
+        let mut cpu = cpu_with(&[0xFB, 0x00, 0x00]);
+        set_interrupts(&mut cpu, 0x04, 0x04);
+        cpu.step();
+        assert!(!cpu.ime);
+        cpu.step();
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, 0x0152);
+
+        // Dispatch pushes the next instruction's address and takes 5 cycles
+        let total_cycles = cpu.total_cycles;
+        assert_eq!(cpu.step(), 5);
+        assert_eq!(cpu.total_cycles, total_cycles + 5);
+        assert_eq!(cpu.pc, 0x0050);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pop(), 0x0152);
+        assert_eq!(cpu.bus.read_byte(IF_ADDRESS as u16) & 0x1F, 0x00);
+    }
+
+    #[test]
+    fn di_cancels_a_pending_ei() {
+        // EI; DI; NOP
+        let mut cpu = cpu_with(&[0xFB, 0xF3, 0x00]);
+        set_interrupts(&mut cpu, 0x04, 0x04);
+        run(&mut cpu, 3);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pc, 0x0153);
+    }
+
+    #[test]
+    fn highest_priority_interrupt_is_dispatched_first() {
+        // EI; NOP
+        let mut cpu = cpu_with(&[0xFB, 0x00]);
+        set_interrupts(&mut cpu, 0x1F, 0b0001_1110);
+        run(&mut cpu, 3);
+        assert_eq!(cpu.pc, Interrupt::LCDStat.vector());
+        assert_eq!(cpu.bus.read_byte(IF_ADDRESS as u16) & 0x1F, 0b0001_1100);
+    }
+
+    #[test]
+    fn halt_wakes_without_dispatch_when_ime_is_clear() {
+        // HALT; INC B
+        let mut cpu = cpu_with(&[0x76, 0x04]);
+        set_interrupts(&mut cpu, 0x04, 0x00);
+        let b = cpu.registers.b;
+        cpu.step();
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), 1);
+            assert_eq!(cpu.pc, 0x0151);
+        }
+
+        cpu.bus.write_byte(IF_ADDRESS as u16, 0x04);
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0152);
+        assert_eq!(cpu.registers.b, b.wrapping_add(1));
+        // Not serviced, so still requested
+        assert_eq!(cpu.bus.read_byte(IF_ADDRESS as u16) & 0x1F, 0x04);
+    }
+
+    #[test]
+    fn halt_with_an_interrupt_pending_and_ime_clear_repeats_a_byte() {
+        // HALT; INC B; NOP
+        let mut cpu = cpu_with(&[0x76, 0x04, 0x00]);
+        set_interrupts(&mut cpu, 0x04, 0x04);
+        let b = cpu.registers.b;
+        cpu.step();
+        assert!(!cpu.is_halted);
+        run(&mut cpu, 2);
+        assert_eq!(cpu.registers.b, b.wrapping_add(2));
+        assert_eq!(cpu.pc, 0x0152);
+    }
+
+    #[test]
+    fn reti_enables_interrupts_immediately() {
+        // LD HL,0x0160; PUSH HL; RETI
+        let mut cpu = cpu_with(&[0x21, 0x60, 0x01, 0xE5, 0xD9]);
+        set_interrupts(&mut cpu, 0x04, 0x04);
+        run(&mut cpu, 3);
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, 0x0160);
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0050);
+    }
+}
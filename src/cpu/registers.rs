@@ -58,3 +58,9 @@ impl Registers {
         self.l = (value & 0xFF) as u8;
     }
 }
+
+impl Default for Registers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -7,6 +7,8 @@ pub enum ArithmeticTarget {
     E,
     H,
     L,
+    HLI, // value at address in HL
+    D8,  // direct 8-bit value
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -26,6 +28,7 @@ pub enum IncDecTarget {
     E,
     H,
     L,
+    HLI,
     BC,
     DE,
     HL,
@@ -86,7 +89,33 @@ pub enum StackTarget {
     AF,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoadWordTarget {
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Indirect {
+    BCIndirect,
+    DEIndirect,
+    HLIndirectMinus,  // (HL-), decrements HL after the access
+    HLIndirectPlus,   // (HL+), increments HL after the access
+    WordIndirect,     // direct 16-bit address
+    LastByteIndirect, // 0xFF00 + C
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum LoadType {
     Byte(LoadByteTarget, LoadByteSource),
+    Word(LoadWordTarget), // direct 16-bit value into register pair
+    AFromIndirect(Indirect),
+    IndirectFromA(Indirect),
+    AFromByteAddress, // A = (0xFF00 + direct 8-bit value)
+    ByteAddressFromA, // (0xFF00 + direct 8-bit value) = A
+    IndirectFromSP,   // (direct 16-bit address) = SP
+    SPFromHL,
+    HLFromSPN, // HL = SP + signed 8-bit value
 }
@@ -0,0 +1,369 @@
+use super::sprite::{Sprite, SPRITE_X_OFFSET};
+use super::tile_attributes::TileAttributes;
+use super::{
+    tile_map_address, TilePixelValue, GPU, SCREEN_WIDTH, SPRITES_PER_LINE, TILE_MAP_WIDTH,
+    WINDOW_X_OFFSET,
+};
+
+// Each fetcher step other than pushing takes two dots
+const FETCH_STEP_DOTS: u8 = 2;
+// Reading a sprite's tile number and both data bytes
+const SPRITE_FETCH_DOTS: u8 = 6;
+const TILE_BYTES: usize = 16;
+
+// How the GPU turns VRAM into pixels during mode 3
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Renderer {
+    // Draws each line in one go when mode 3 ends; mode 3 always lasts 172 dots
+    Scanline,
+    // Shifts out one pixel per dot through the background and sprite FIFOs,
+    // so mid-line register writes land on the right pixel and mode 3 grows
+    // with fine scroll, the window and sprites
+    PixelFifo,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FetchStep {
+    TileNumber,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct SpritePixel {
+    value: TilePixelValue,
+    sprite: Sprite,
+    slot: usize, // position in OAM order among the line's sprites
+}
+
+// Background/window fetcher and the two FIFOs it feeds, reset on every line
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PixelFifo {
+    step: FetchStep,
+    step_dots: u8,
+    fetch_x: u8, // tile column, relative to SCX or the window's left edge
+    tile_number: u8,
+    tile_attributes: TileAttributes,
+    data_low: u8,
+    data_high: u8,
+    first_fetch: bool, // the line's first fetch is thrown away
+    window: bool,      // fetching window tiles instead of background
+    background: [TilePixelValue; 8],
+    background_len: usize,
+    background_attributes: TileAttributes, // shared by everything in the FIFO
+    sprites: [Option<SpritePixel>; 8],     // lined up with the next 8 pixels
+    line_sprites: [Option<Sprite>; SPRITES_PER_LINE],
+    fetched_sprites: [bool; SPRITES_PER_LINE],
+    sprite_fetch: Option<(usize, u8)>, // sprite slot and dots left
+    discard: u8,                       // pixels shifted out but not shown
+    x: u8,                             // next pixel on the LCD
+}
+
+impl PixelFifo {
+    pub fn new() -> Self {
+        PixelFifo {
+            step: FetchStep::TileNumber,
+            step_dots: 0,
+            fetch_x: 0,
+            tile_number: 0,
+            tile_attributes: TileAttributes::new(),
+            data_low: 0,
+            data_high: 0,
+            first_fetch: true,
+            window: false,
+            background: [TilePixelValue::Zero; 8],
+            background_len: 0,
+            background_attributes: TileAttributes::new(),
+            sprites: [None; 8],
+            line_sprites: [None; SPRITES_PER_LINE],
+            fetched_sprites: [false; SPRITES_PER_LINE],
+            sprite_fetch: None,
+            discard: 0,
+            x: 0,
+        }
+    }
+
+    pub fn line_done(&self) -> bool {
+        self.x as usize == SCREEN_WIDTH
+    }
+
+    // Restarts the fetcher, e.g. on the window's first pixel
+    fn restart_fetch(&mut self, window: bool) {
+        self.step = FetchStep::TileNumber;
+        self.step_dots = 0;
+        self.fetch_x = 0;
+        self.window = window;
+        self.background_len = 0;
+    }
+
+    // The fetcher holds a tile that has to wait for the FIFO to empty
+    fn fetcher_waiting(&self) -> bool {
+        self.step == FetchStep::Push && self.background_len > 0
+    }
+
+    fn pop_background(&mut self) -> TilePixelValue {
+        let pixel = self.background[self.background.len() - self.background_len];
+        self.background_len -= 1;
+        pixel
+    }
+
+    fn pop_sprite(&mut self) -> Option<SpritePixel> {
+        let pixel = self.sprites[0];
+        self.sprites.rotate_left(1);
+        self.sprites[7] = None;
+        pixel
+    }
+}
+
+impl Default for PixelFifo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Color number of bit `bit` (7 is leftmost) of a tile row
+fn tile_row_pixel(low: u8, high: u8, bit: u8) -> TilePixelValue {
+    match ((high >> bit) & 1, (low >> bit) & 1) {
+        (1, 1) => TilePixelValue::Three,
+        (1, 0) => TilePixelValue::Two,
+        (0, 1) => TilePixelValue::One,
+        _ => TilePixelValue::Zero,
+    }
+}
+
+impl GPU {
+    // Sets up the FIFOs as mode 3 begins on the current line
+    pub(super) fn start_pixel_fifo(&mut self) {
+        let mut fifo = PixelFifo::new();
+        for (slot, sprite) in fifo.line_sprites.iter_mut().zip(self.line_sprites()) {
+            *slot = Some(sprite);
+        }
+        // Fine scroll is applied by dropping the first pixels of the line
+        fifo.discard = self.scx % 8;
+        self.fifo = fifo;
+    }
+
+    // One dot of mode 3. Fetching a sprite stalls the LCD, and so does a
+    // background FIFO that is empty after a window restart.
+    pub(super) fn tick_pixel_fifo(&mut self) {
+        if self.fifo.sprite_fetch.is_none() && self.lcdc.objects_enabled {
+            self.fifo.sprite_fetch = self.next_sprite().map(|slot| (slot, SPRITE_FETCH_DOTS));
+        }
+        if let Some((slot, dots)) = self.fifo.sprite_fetch {
+            // The background fetch in progress has to finish first. The
+            // sprite fetch starts on the dot it does, and the background
+            // fetcher stays put until the sprite is in the FIFO.
+            if !self.fifo.fetcher_waiting() {
+                self.tick_fetcher();
+                if !self.fifo.fetcher_waiting() {
+                    return;
+                }
+            }
+            if dots > 1 {
+                self.fifo.sprite_fetch = Some((slot, dots - 1));
+            } else {
+                self.fetch_sprite(slot);
+                self.fifo.sprite_fetch = None;
+            }
+            return;
+        }
+
+        if self.fifo.background_len > 0 {
+            self.shift_pixel();
+        }
+        self.tick_fetcher();
+    }
+
+    // Unfetched sprite whose left edge is at the next pixel, first in OAM order.
+    // Sprites at X=0 are fully hidden but still fetched, before any fine
+    // scroll is applied; the rest wait for the scrolled-out pixels to go.
+    fn next_sprite(&self) -> Option<usize> {
+        let fifo = &self.fifo;
+        fifo.line_sprites
+            .iter()
+            .zip(fifo.fetched_sprites)
+            .position(|(sprite, fetched)| {
+                !fetched
+                    && sprite.is_some_and(|sprite| {
+                        (sprite.x == 0 && fifo.x == 0)
+                            || (fifo.discard == 0
+                                && sprite.x.saturating_sub(SPRITE_X_OFFSET) == fifo.x)
+                    })
+            })
+    }
+
+    fn window_starts_here(&self) -> bool {
+        self.background_shown()
+            && self.lcdc.window_enabled
+            && self.window_y_triggered
+            && !self.fifo.window
+            && self.fifo.x + WINDOW_X_OFFSET >= self.wx
+            && self.wx < SCREEN_WIDTH as u8 + WINDOW_X_OFFSET
+    }
+
+    fn shift_pixel(&mut self) {
+        if self.fifo.discard == 0 && self.window_starts_here() {
+            // The window hides any pixels left of the screen edge
+            if self.fifo.x == 0 {
+                self.fifo.discard = WINDOW_X_OFFSET.saturating_sub(self.wx);
+            }
+            self.fifo.restart_fetch(true);
+            self.window_drawn = true;
+            return;
+        }
+
+        let background = self.fifo.pop_background();
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return;
+        }
+        let sprite = self.fifo.pop_sprite();
+
+        // Palettes and enable bits are read as each pixel leaves the FIFO
+        let background = if self.background_shown() {
+            background
+        } else {
+            TilePixelValue::Zero
+        };
+        let attributes = self.fifo.background_attributes;
+        let color = match sprite {
+            Some(pixel)
+                if self.lcdc.objects_enabled
+                    && pixel.value != TilePixelValue::Zero
+                    && !self.background_over_sprite(background, attributes, &pixel.sprite) =>
+            {
+                self.sprite_color(pixel.value, &pixel.sprite)
+            }
+            _ => self.background_color(background, attributes),
+        };
+        self.framebuffer[self.line as usize * SCREEN_WIDTH + self.fifo.x as usize] = color;
+        self.fifo.x += 1;
+    }
+
+    fn tick_fetcher(&mut self) {
+        let step = self.fifo.step;
+        if step != FetchStep::Push {
+            self.fifo.step_dots += 1;
+            if self.fifo.step_dots < FETCH_STEP_DOTS {
+                return;
+            }
+            self.fifo.step_dots = 0;
+        }
+        match step {
+            FetchStep::TileNumber => {
+                let address = self.fetcher_map_address();
+                self.fifo.tile_number = self.vram[0][address];
+                self.fifo.tile_attributes = self.tile_attributes(address);
+                self.fifo.step = FetchStep::DataLow;
+            }
+            FetchStep::DataLow => {
+                let (bank, address) = self.fetcher_data_address();
+                self.fifo.data_low = self.vram[bank][address];
+                self.fifo.step = FetchStep::DataHigh;
+            }
+            FetchStep::DataHigh => {
+                let (bank, address) = self.fetcher_data_address();
+                self.fifo.data_high = self.vram[bank][address + 1];
+                self.fifo.step = FetchStep::Push;
+                self.push_background();
+            }
+            FetchStep::Push => self.push_background(),
+        }
+    }
+
+    // Tiles are only pushed into an empty FIFO
+    fn push_background(&mut self) {
+        let fifo = &mut self.fifo;
+        if fifo.background_len > 0 {
+            return;
+        }
+        fifo.step = FetchStep::TileNumber;
+        if fifo.first_fetch {
+            fifo.first_fetch = false;
+            return;
+        }
+        for (column, pixel) in fifo.background.iter_mut().enumerate() {
+            let bit = if fifo.tile_attributes.x_flip {
+                column as u8
+            } else {
+                7 - column as u8
+            };
+            *pixel = tile_row_pixel(fifo.data_low, fifo.data_high, bit);
+        }
+        fifo.background_attributes = fifo.tile_attributes;
+        fifo.background_len = fifo.background.len();
+        fifo.fetch_x = fifo.fetch_x.wrapping_add(1);
+    }
+
+    // SCX and SCY are read at every fetch
+    fn fetcher_map_address(&self) -> usize {
+        let (high_map, column, row) = if self.fifo.window {
+            (
+                self.lcdc.window_tile_map,
+                self.fifo.fetch_x,
+                self.window_line,
+            )
+        } else {
+            let column = (self.scx / 8).wrapping_add(self.fifo.fetch_x);
+            (
+                self.lcdc.bg_tile_map,
+                column,
+                self.line.wrapping_add(self.scy),
+            )
+        };
+        tile_map_address(high_map, column as usize % TILE_MAP_WIDTH, row as usize / 8)
+    }
+
+    // VRAM bank and offset of the low byte of the tile row being fetched
+    fn fetcher_data_address(&self) -> (usize, usize) {
+        let y = if self.fifo.window {
+            self.window_line
+        } else {
+            self.line.wrapping_add(self.scy)
+        };
+        let attributes = self.fifo.tile_attributes;
+        let row = if attributes.y_flip { 7 - y % 8 } else { y % 8 } as usize;
+        let address = self.tile_index(self.fifo.tile_number) * TILE_BYTES + row * 2;
+        (attributes.vram_bank as usize, address)
+    }
+
+    // Mixes a sprite into the sprite FIFO. Pixels already there came from
+    // sprites further left, which win on DMG, so only transparent ones are
+    // replaced. In CGB mode the lower OAM index wins instead.
+    fn fetch_sprite(&mut self, slot: usize) {
+        self.fifo.fetched_sprites[slot] = true;
+        let sprite = match self.fifo.line_sprites[slot] {
+            Some(sprite) => sprite,
+            None => return,
+        };
+        let (tile, row) = self.sprite_row(&sprite);
+        let address = tile * TILE_BYTES + row * 2;
+        let bank = self.sprite_bank(&sprite);
+        let (low, high) = (self.vram[bank][address], self.vram[bank][address + 1]);
+
+        // Sprites hanging off the left edge lose their first columns
+        let hidden = SPRITE_X_OFFSET.saturating_sub(sprite.x);
+        for column in hidden..8 {
+            let bit = if sprite.attributes.x_flip {
+                column
+            } else {
+                7 - column
+            };
+            let pixel = SpritePixel {
+                value: tile_row_pixel(low, high, bit),
+                sprite,
+                slot,
+            };
+            let cgb_mode = self.cgb_mode;
+            let existing = &mut self.fifo.sprites[(column - hidden) as usize];
+            let replace = existing.is_none_or(|existing| {
+                existing.value == TilePixelValue::Zero
+                    || (cgb_mode && pixel.value != TilePixelValue::Zero && slot < existing.slot)
+            });
+            if replace {
+                *existing = Some(pixel);
+            }
+        }
+    }
+}
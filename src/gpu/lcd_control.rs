@@ -0,0 +1,70 @@
+const LCD_ENABLE_BYTE_POSITION: u8 = 7;
+const WINDOW_TILE_MAP_BYTE_POSITION: u8 = 6;
+const WINDOW_ENABLE_BYTE_POSITION: u8 = 5;
+const TILE_DATA_BYTE_POSITION: u8 = 4;
+const BG_TILE_MAP_BYTE_POSITION: u8 = 3;
+const OBJ_SIZE_BYTE_POSITION: u8 = 2;
+const OBJ_ENABLE_BYTE_POSITION: u8 = 1;
+const BG_ENABLE_BYTE_POSITION: u8 = 0;
+
+// LCDC (0xFF40)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LCDControl {
+    pub lcd_enabled: bool,
+    pub window_tile_map: bool, // 0x9C00 instead of 0x9800
+    pub window_enabled: bool,
+    pub tile_data: bool,    // 0x8000 unsigned addressing instead of 0x8800 signed
+    pub bg_tile_map: bool,  // 0x9C00 instead of 0x9800
+    pub tall_objects: bool, // 8x16 instead of 8x8
+    pub objects_enabled: bool,
+    pub bg_enabled: bool,
+}
+
+impl LCDControl {
+    pub fn new() -> Self {
+        LCDControl {
+            lcd_enabled: false,
+            window_tile_map: false,
+            window_enabled: false,
+            tile_data: false,
+            bg_tile_map: false,
+            tall_objects: false,
+            objects_enabled: false,
+            bg_enabled: false,
+        }
+    }
+}
+
+impl Default for LCDControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::convert::From<LCDControl> for u8 {
+    fn from(lcdc: LCDControl) -> Self {
+        (if lcdc.lcd_enabled { 1 } else { 0 }) << LCD_ENABLE_BYTE_POSITION
+            | (if lcdc.window_tile_map { 1 } else { 0 }) << WINDOW_TILE_MAP_BYTE_POSITION
+            | (if lcdc.window_enabled { 1 } else { 0 }) << WINDOW_ENABLE_BYTE_POSITION
+            | (if lcdc.tile_data { 1 } else { 0 }) << TILE_DATA_BYTE_POSITION
+            | (if lcdc.bg_tile_map { 1 } else { 0 }) << BG_TILE_MAP_BYTE_POSITION
+            | (if lcdc.tall_objects { 1 } else { 0 }) << OBJ_SIZE_BYTE_POSITION
+            | (if lcdc.objects_enabled { 1 } else { 0 }) << OBJ_ENABLE_BYTE_POSITION
+            | (if lcdc.bg_enabled { 1 } else { 0 }) << BG_ENABLE_BYTE_POSITION
+    }
+}
+
+impl std::convert::From<u8> for LCDControl {
+    fn from(byte: u8) -> Self {
+        LCDControl {
+            lcd_enabled: ((byte >> LCD_ENABLE_BYTE_POSITION) & 0b1) != 0,
+            window_tile_map: ((byte >> WINDOW_TILE_MAP_BYTE_POSITION) & 0b1) != 0,
+            window_enabled: ((byte >> WINDOW_ENABLE_BYTE_POSITION) & 0b1) != 0,
+            tile_data: ((byte >> TILE_DATA_BYTE_POSITION) & 0b1) != 0,
+            bg_tile_map: ((byte >> BG_TILE_MAP_BYTE_POSITION) & 0b1) != 0,
+            tall_objects: ((byte >> OBJ_SIZE_BYTE_POSITION) & 0b1) != 0,
+            objects_enabled: ((byte >> OBJ_ENABLE_BYTE_POSITION) & 0b1) != 0,
+            bg_enabled: ((byte >> BG_ENABLE_BYTE_POSITION) & 0b1) != 0,
+        }
+    }
+}
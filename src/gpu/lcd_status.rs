@@ -0,0 +1,51 @@
+const LYC_INTERRUPT_BYTE_POSITION: u8 = 6;
+const OAM_INTERRUPT_BYTE_POSITION: u8 = 5;
+const VBLANK_INTERRUPT_BYTE_POSITION: u8 = 4;
+const HBLANK_INTERRUPT_BYTE_POSITION: u8 = 3;
+
+// Writable part of STAT (0xFF41): which conditions raise the STAT interrupt.
+// The mode and LY=LYC bits are read-only and come from the GPU itself.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LCDStatus {
+    pub lyc_interrupt: bool,
+    pub oam_interrupt: bool,
+    pub vblank_interrupt: bool,
+    pub hblank_interrupt: bool,
+}
+
+impl LCDStatus {
+    pub fn new() -> Self {
+        LCDStatus {
+            lyc_interrupt: false,
+            oam_interrupt: false,
+            vblank_interrupt: false,
+            hblank_interrupt: false,
+        }
+    }
+}
+
+impl Default for LCDStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::convert::From<LCDStatus> for u8 {
+    fn from(stat: LCDStatus) -> Self {
+        (if stat.lyc_interrupt { 1 } else { 0 }) << LYC_INTERRUPT_BYTE_POSITION
+            | (if stat.oam_interrupt { 1 } else { 0 }) << OAM_INTERRUPT_BYTE_POSITION
+            | (if stat.vblank_interrupt { 1 } else { 0 }) << VBLANK_INTERRUPT_BYTE_POSITION
+            | (if stat.hblank_interrupt { 1 } else { 0 }) << HBLANK_INTERRUPT_BYTE_POSITION
+    }
+}
+
+impl std::convert::From<u8> for LCDStatus {
+    fn from(byte: u8) -> Self {
+        LCDStatus {
+            lyc_interrupt: ((byte >> LYC_INTERRUPT_BYTE_POSITION) & 0b1) != 0,
+            oam_interrupt: ((byte >> OAM_INTERRUPT_BYTE_POSITION) & 0b1) != 0,
+            vblank_interrupt: ((byte >> VBLANK_INTERRUPT_BYTE_POSITION) & 0b1) != 0,
+            hblank_interrupt: ((byte >> HBLANK_INTERRUPT_BYTE_POSITION) & 0b1) != 0,
+        }
+    }
+}
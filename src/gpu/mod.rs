@@ -1,6 +1,91 @@
+pub mod fifo;
+pub mod lcd_control;
+pub mod lcd_status;
+pub mod palette;
+pub mod sprite;
+pub mod tile_attributes;
+
+use crate::interrupts::{Interrupt, InterruptFlags};
+
+use self::fifo::{PixelFifo, Renderer};
+use self::lcd_control::LCDControl;
+use self::lcd_status::LCDStatus;
+use self::palette::ColorPalettes;
+use self::sprite::{Sprite, SPRITE_SIZE, SPRITE_X_OFFSET, SPRITE_Y_OFFSET};
+use self::tile_attributes::TileAttributes;
+
 pub const VRAM_BEGIN: usize = 0x8000;
 pub const VRAM_END: usize = 0x9FFF;
 pub const VRAM_SIZE: usize = VRAM_END - VRAM_BEGIN + 1;
+// CGB has a second VRAM bank, selected through VBK
+const VRAM_BANKS: usize = 2;
+pub const OAM_BEGIN: usize = 0xFE00;
+pub const OAM_END: usize = 0xFE9F;
+pub const OAM_SIZE: usize = OAM_END - OAM_BEGIN + 1;
+
+pub const LCDC_ADDRESS: usize = 0xFF40;
+pub const STAT_ADDRESS: usize = 0xFF41;
+pub const SCY_ADDRESS: usize = 0xFF42;
+pub const SCX_ADDRESS: usize = 0xFF43;
+pub const LY_ADDRESS: usize = 0xFF44;
+pub const LYC_ADDRESS: usize = 0xFF45;
+pub const BGP_ADDRESS: usize = 0xFF47;
+pub const OBP0_ADDRESS: usize = 0xFF48;
+pub const OBP1_ADDRESS: usize = 0xFF49;
+pub const WY_ADDRESS: usize = 0xFF4A;
+pub const WX_ADDRESS: usize = 0xFF4B;
+pub const VBK_ADDRESS: usize = 0xFF4F;
+pub const BCPS_ADDRESS: usize = 0xFF68;
+pub const BCPD_ADDRESS: usize = 0xFF69;
+pub const OCPS_ADDRESS: usize = 0xFF6A;
+pub const OCPD_ADDRESS: usize = 0xFF6B;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// Dots run at the 4 MiHz clock rate
+const DOTS_PER_CYCLE: u8 = 4;
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const PIXEL_TRANSFER_DOTS: u16 = 172;
+const VISIBLE_LINES: u8 = SCREEN_HEIGHT as u8;
+const LINES_PER_FRAME: u8 = 154;
+// Dots into the last line after which LY already reads 0
+const LAST_LINE_LY_RESET_DOTS: u16 = 4;
+
+const STAT_COINCIDENCE_BYTE_POSITION: u8 = 2;
+
+// Tile maps are 32x32 tile indices, at 0x9800 or 0x9C00
+const TILE_MAP_LOW: usize = 0x1800;
+const TILE_MAP_HIGH: usize = 0x1C00;
+const TILE_MAP_WIDTH: usize = 32;
+// WX is the window's left edge plus 7
+const WINDOW_X_OFFSET: u8 = 7;
+
+// OAM scan keeps at most this many sprites per line
+const SPRITES_PER_LINE: usize = 10;
+// OAM scan reads one 8-byte row (two sprites) per machine cycle
+const OAM_ROW_SIZE: usize = 8;
+
+// PPU mode, as reported in the low bits of STAT
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    HBlank,
+    VBlank,
+    OAMScan,
+    PixelTransfer,
+}
+
+impl std::convert::From<Mode> for u8 {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::HBlank => 0,
+            Mode::VBlank => 1,
+            Mode::OAMScan => 2,
+            Mode::PixelTransfer => 3,
+        }
+    }
+}
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TilePixelValue {
@@ -10,34 +95,628 @@ pub enum TilePixelValue {
     Three,
 }
 
-type Tile = [[TilePixelValue; 8]; 8];
+impl TilePixelValue {
+    // Index of the color within a palette register
+    fn palette_index(&self) -> u8 {
+        match self {
+            TilePixelValue::Zero => 0,
+            TilePixelValue::One => 1,
+            TilePixelValue::Two => 2,
+            TilePixelValue::Three => 3,
+        }
+    }
+}
+
+// Gray level of a DMG screen pixel
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shade {
+    White,
+    LightGray,
+    DarkGray,
+    Black,
+}
+
+impl Shade {
+    // Shade a monochrome palette register (BGP, OBP0, OBP1) assigns to `value`
+    fn from_palette(palette: u8, value: TilePixelValue) -> Self {
+        match (palette >> (value.palette_index() * 2)) & 0b11 {
+            0 => Shade::White,
+            1 => Shade::LightGray,
+            2 => Shade::DarkGray,
+            _ => Shade::Black,
+        }
+    }
+}
+
+// Grays as RGB555, for the framebuffer
+impl std::convert::From<Shade> for u16 {
+    fn from(shade: Shade) -> Self {
+        match shade {
+            Shade::White => 0x7FFF,
+            Shade::LightGray => 0x56B5,
+            Shade::DarkGray => 0x294A,
+            Shade::Black => 0x0000,
+        }
+    }
+}
+
+// Tile data spans 0x8000-0x97FF, 16 bytes per tile
+const TILE_SET_END: usize = 0x1800;
+const TILE_COUNT: usize = TILE_SET_END / 16;
+// Both banks' tiles are cached, bank 1 after bank 0
+const TILE_SET_SIZE: usize = TILE_COUNT * VRAM_BANKS;
+
+// 8 rows of 8 pixels
+pub type Tile = [[TilePixelValue; 8]; 8];
 fn empty_tile() -> Tile {
     [[TilePixelValue::Zero; 8]; 8]
 }
 
+// VRAM offset of the entry for tile column `x`, row `y` of a tile map
+fn tile_map_address(high_map: bool, x: usize, y: usize) -> usize {
+    let map = if high_map {
+        TILE_MAP_HIGH
+    } else {
+        TILE_MAP_LOW
+    };
+    map + y * TILE_MAP_WIDTH + x
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct GPU {
-    vram: [u8; VRAM_SIZE],
-    tile_set: [Tile; 384],
+    vram: [[u8; VRAM_SIZE]; VRAM_BANKS],
+    vram_bank: usize,    // bank the CPU sees
+    cgb_mode: bool,      // running a CGB game, which unlocks the CGB registers
+    oam: [u8; OAM_SIZE], // object attribute memory
+    tile_set: [Tile; TILE_SET_SIZE],
+    lcdc: LCDControl,
+    stat: LCDStatus,
+    scy: u8,
+    scx: u8,
+    ly: u8, // as visible to the CPU
+    lyc: u8,
+    line: u8, // scanline being drawn, which LY lags on the last line
+    line_dots: u16,
+    mode: Mode,
+    stat_line: bool,               // STAT interrupt fires on this going high
+    first_line_after_enable: bool, // line 0 skips OAM scan after the LCD turns on
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+    window_line: u8, // window row to draw next, only advances on lines showing it
+    window_y_triggered: bool, // LY has matched WY this frame
+    window_drawn: bool, // the window showed up on the current line
+    bg_palettes: ColorPalettes, // BCPS/BCPD
+    obj_palettes: ColorPalettes, // OCPS/OCPD
+    renderer: Renderer,
+    line_renderer: Renderer, // renderer drawing the current line
+    transfer_done: bool,     // mode 3 is over for the current line
+    fifo: PixelFifo,
+    framebuffer: [u16; SCREEN_WIDTH * SCREEN_HEIGHT], // RGB555
 }
 
 impl GPU {
     pub fn new() -> Self {
         GPU {
-            vram: [0; VRAM_SIZE],
-            tile_set: [empty_tile(); 384],
+            vram: [[0; VRAM_SIZE]; VRAM_BANKS],
+            vram_bank: 0,
+            cgb_mode: false,
+            oam: [0; OAM_SIZE],
+            tile_set: [empty_tile(); TILE_SET_SIZE],
+            lcdc: LCDControl::new(),
+            stat: LCDStatus::new(),
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            line: 0,
+            line_dots: 0,
+            mode: Mode::HBlank,
+            stat_line: false,
+            first_line_after_enable: false,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            window_line: 0,
+            window_y_triggered: false,
+            window_drawn: false,
+            bg_palettes: ColorPalettes::new(),
+            obj_palettes: ColorPalettes::new(),
+            renderer: Renderer::Scanline,
+            line_renderer: Renderer::Scanline,
+            transfer_done: false,
+            fifo: PixelFifo::new(),
+            framebuffer: [Shade::White.into(); SCREEN_WIDTH * SCREEN_HEIGHT],
+        }
+    }
+
+    // Last rendered frame, row by row, as RGB555 colors. Outside CGB mode
+    // these are the four DMG shades.
+    pub fn framebuffer(&self) -> &[u16] {
+        &self.framebuffer
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    // Takes effect from the next line
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    // Advances the PPU by `cycles` machine cycles
+    pub fn step(&mut self, cycles: u8, interrupt_flag: &mut InterruptFlags) {
+        if !self.lcdc.lcd_enabled {
+            return;
+        }
+        for _ in 0..cycles as u16 * DOTS_PER_CYCLE as u16 {
+            self.tick(interrupt_flag);
         }
     }
 
+    fn tick(&mut self, interrupt_flag: &mut InterruptFlags) {
+        self.line_dots += 1;
+        if self.line_dots == DOTS_PER_LINE {
+            self.line_dots = 0;
+            self.line = (self.line + 1) % LINES_PER_FRAME;
+            self.ly = self.line;
+            self.first_line_after_enable = false;
+            self.transfer_done = false;
+            if self.line == 0 {
+                self.start_frame();
+            }
+        }
+        if self.line == LINES_PER_FRAME - 1 && self.line_dots == LAST_LINE_LY_RESET_DOTS {
+            self.ly = 0;
+        }
+
+        let mode = self.current_mode();
+        if mode != self.mode {
+            match mode {
+                Mode::PixelTransfer => self.start_pixel_transfer(),
+                Mode::HBlank if self.mode == Mode::PixelTransfer => self.finish_pixel_transfer(),
+                Mode::VBlank => interrupt_flag.request(Interrupt::VBlank),
+                _ => {}
+            }
+            self.mode = mode;
+        }
+        if self.mode == Mode::PixelTransfer {
+            self.transfer_done = match self.line_renderer {
+                Renderer::Scanline => self.line_dots + 1 == OAM_SCAN_DOTS + PIXEL_TRANSFER_DOTS,
+                Renderer::PixelFifo => {
+                    self.tick_pixel_fifo();
+                    self.fifo.line_done()
+                }
+            };
+        }
+        self.update_stat_line(interrupt_flag);
+    }
+
+    fn start_pixel_transfer(&mut self) {
+        if self.line == self.wy {
+            self.window_y_triggered = true;
+        }
+        self.window_drawn = false;
+        self.line_renderer = self.renderer;
+        if self.line_renderer == Renderer::PixelFifo {
+            self.start_pixel_fifo();
+        }
+    }
+
+    fn finish_pixel_transfer(&mut self) {
+        if self.line_renderer == Renderer::Scanline {
+            self.render_scanline();
+        }
+        if self.window_drawn {
+            self.window_line += 1;
+        }
+    }
+
+    fn start_frame(&mut self) {
+        self.window_line = 0;
+        self.window_y_triggered = false;
+    }
+
+    fn render_scanline(&mut self) {
+        let line = self.line;
+        let window_visible = self.lcdc.window_enabled
+            && self.window_y_triggered
+            && self.wx < SCREEN_WIDTH as u8 + WINDOW_X_OFFSET;
+
+        // Color numbers before the palette and the attributes they came with,
+        // which sprite priority looks at
+        let mut bg_values = [TilePixelValue::Zero; SCREEN_WIDTH];
+        let mut bg_attributes = [TileAttributes::new(); SCREEN_WIDTH];
+        for x in 0..SCREEN_WIDTH as u8 {
+            // With the background disabled, background and window are blank,
+            // except in CGB mode where LCDC bit 0 is about priority instead
+            let (value, attributes) = if !self.background_shown() {
+                (TilePixelValue::Zero, TileAttributes::new())
+            } else if window_visible && x + WINDOW_X_OFFSET >= self.wx {
+                let window_x = x + WINDOW_X_OFFSET - self.wx;
+                self.tile_map_pixel(self.lcdc.window_tile_map, window_x, self.window_line)
+            } else {
+                let bg_x = x.wrapping_add(self.scx);
+                let bg_y = line.wrapping_add(self.scy);
+                self.tile_map_pixel(self.lcdc.bg_tile_map, bg_x, bg_y)
+            };
+            bg_values[x as usize] = value;
+            bg_attributes[x as usize] = attributes;
+        }
+
+        let row = line as usize * SCREEN_WIDTH;
+        for x in 0..SCREEN_WIDTH {
+            self.framebuffer[row + x] = self.background_color(bg_values[x], bg_attributes[x]);
+        }
+        if self.lcdc.objects_enabled {
+            self.render_sprites(&bg_values, &bg_attributes);
+        }
+
+        self.window_drawn = window_visible && self.background_shown();
+    }
+
+    // On DMG, LCDC bit 0 blanks the background and window
+    fn background_shown(&self) -> bool {
+        self.cgb_mode || self.lcdc.bg_enabled
+    }
+
+    // Whether a background pixel hides an opaque sprite pixel. In CGB mode
+    // clearing LCDC bit 0 puts every sprite on top; otherwise either the tile
+    // or the sprite attributes can put colors 1-3 of the background in front.
+    fn background_over_sprite(
+        &self,
+        value: TilePixelValue,
+        attributes: TileAttributes,
+        sprite: &Sprite,
+    ) -> bool {
+        if value == TilePixelValue::Zero {
+            return false;
+        }
+        if self.cgb_mode {
+            self.lcdc.bg_enabled && (attributes.priority || sprite.attributes.behind_background)
+        } else {
+            sprite.attributes.behind_background
+        }
+    }
+
+    fn background_color(&self, value: TilePixelValue, attributes: TileAttributes) -> u16 {
+        if self.cgb_mode {
+            self.bg_palettes.color(attributes.palette, value)
+        } else {
+            Shade::from_palette(self.bgp, value).into()
+        }
+    }
+
+    fn sprite_color(&self, value: TilePixelValue, sprite: &Sprite) -> u16 {
+        if self.cgb_mode {
+            return self
+                .obj_palettes
+                .color(sprite.attributes.cgb_palette, value);
+        }
+        let palette = if sprite.attributes.palette {
+            self.obp1
+        } else {
+            self.obp0
+        };
+        Shade::from_palette(palette, value).into()
+    }
+
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc.tall_objects {
+            16
+        } else {
+            8
+        }
+    }
+
+    // First 10 sprites in OAM order that overlap the current line. X doesn't
+    // matter here, so off-screen sprites still use up slots.
+    fn line_sprites(&self) -> Vec<Sprite> {
+        let height = self.sprite_height();
+        self.oam
+            .chunks_exact(SPRITE_SIZE)
+            .map(Sprite::from_oam)
+            .filter(|sprite| sprite.on_line(self.line, height))
+            .take(SPRITES_PER_LINE)
+            .collect()
+    }
+
+    fn render_sprites(
+        &mut self,
+        bg_values: &[TilePixelValue; SCREEN_WIDTH],
+        bg_attributes: &[TileAttributes; SCREEN_WIDTH],
+    ) {
+        let mut sprites = self.line_sprites();
+        // Lower X wins, then lower OAM index (the sort is stable); in CGB mode
+        // only OAM order counts. Each pixel belongs to the first sprite in that
+        // order that is opaque there.
+        if !self.cgb_mode {
+            sprites.sort_by_key(|sprite| sprite.x);
+        }
+        let row = self.line as usize * SCREEN_WIDTH;
+        let mut drawn = [false; SCREEN_WIDTH];
+
+        for sprite in sprites {
+            let (tile, tile_row) = self.sprite_row(&sprite);
+            let tile = self.sprite_bank(&sprite) * TILE_COUNT + tile;
+            for column in 0..8u8 {
+                let screen_x = sprite.x as i16 - SPRITE_X_OFFSET as i16 + column as i16;
+                if !(0..SCREEN_WIDTH as i16).contains(&screen_x) {
+                    continue;
+                }
+                let screen_x = screen_x as usize;
+                // A higher priority sprite already owns this pixel
+                if drawn[screen_x] {
+                    continue;
+                }
+
+                let pixel_x = if sprite.attributes.x_flip {
+                    7 - column
+                } else {
+                    column
+                };
+                let value = self.tile_set[tile][tile_row][pixel_x as usize];
+                // Color 0 is transparent and lets lower priority sprites through
+                if value == TilePixelValue::Zero {
+                    continue;
+                }
+                drawn[screen_x] = true;
+
+                if self.background_over_sprite(
+                    bg_values[screen_x],
+                    bg_attributes[screen_x],
+                    &sprite,
+                ) {
+                    continue;
+                }
+                self.framebuffer[row + screen_x] = self.sprite_color(value, &sprite);
+            }
+        }
+    }
+
+    // VRAM bank holding a sprite's tile data
+    fn sprite_bank(&self, sprite: &Sprite) -> usize {
+        (self.cgb_mode && sprite.attributes.vram_bank) as usize
+    }
+
+    // Tile and row within it that `sprite` shows on the current line. Sprites
+    // always use 0x8000 addressing; 8x16 sprites ignore bit 0 of the tile number.
+    fn sprite_row(&self, sprite: &Sprite) -> (usize, usize) {
+        // The size can change after the line's sprites were picked, in which
+        // case only the row bits the current size uses count
+        let height = self.sprite_height();
+        let mut row = (self.line + SPRITE_Y_OFFSET - sprite.y) & (height - 1);
+        if sprite.attributes.y_flip {
+            row = height - 1 - row;
+        }
+        let tile = if height == 16 {
+            (sprite.tile & 0xFE) as usize + (row / 8) as usize
+        } else {
+            sprite.tile as usize
+        };
+        (tile, (row % 8) as usize)
+    }
+
+    // Pixel at (x, y) of the 256x256 background described by a tile map
+    // and the attributes of its tile (always the defaults outside CGB mode)
+    fn tile_map_pixel(&self, high_map: bool, x: u8, y: u8) -> (TilePixelValue, TileAttributes) {
+        let (x, y) = (x as usize, y as usize);
+        let address = tile_map_address(high_map, x / 8, y / 8);
+        let attributes = self.tile_attributes(address);
+        let tile =
+            attributes.vram_bank as usize * TILE_COUNT + self.tile_index(self.vram[0][address]);
+        let row = if attributes.y_flip { 7 - y % 8 } else { y % 8 };
+        let column = if attributes.x_flip { 7 - x % 8 } else { x % 8 };
+        (self.tile_set[tile][row][column], attributes)
+    }
+
+    // Attributes for the tile map entry at `address`, from VRAM bank 1
+    fn tile_attributes(&self, address: usize) -> TileAttributes {
+        if self.cgb_mode {
+            TileAttributes::from(self.vram[1][address])
+        } else {
+            TileAttributes::new()
+        }
+    }
+
+    // 0x8000 addressing numbers tiles 0-255 from the start of VRAM; 0x8800
+    // addressing treats the number as signed, relative to tile 256
+    fn tile_index(&self, tile_number: u8) -> usize {
+        if self.lcdc.tile_data {
+            tile_number as usize
+        } else {
+            (256 + tile_number as i8 as i16) as usize
+        }
+    }
+
+    fn current_mode(&self) -> Mode {
+        if self.line >= VISIBLE_LINES {
+            Mode::VBlank
+        } else if self.line_dots < OAM_SCAN_DOTS {
+            if self.first_line_after_enable {
+                Mode::HBlank
+            } else {
+                Mode::OAMScan
+            }
+        } else if !self.transfer_done {
+            Mode::PixelTransfer
+        } else {
+            Mode::HBlank
+        }
+    }
+
+    // The STAT interrupt is requested when any enabled condition becomes true
+    // while none were true before
+    fn update_stat_line(&mut self, interrupt_flag: &mut InterruptFlags) {
+        let coincidence = self.lcdc.lcd_enabled && self.ly == self.lyc;
+        let mode_condition = match self.mode {
+            Mode::HBlank => self.stat.hblank_interrupt,
+            // The OAM condition also fires as VBlank begins
+            Mode::VBlank => {
+                self.stat.vblank_interrupt
+                    || (self.stat.oam_interrupt
+                        && self.line == VISIBLE_LINES
+                        && self.line_dots == 0)
+            }
+            Mode::OAMScan => self.stat.oam_interrupt,
+            Mode::PixelTransfer => false,
+        };
+        let stat_line =
+            self.lcdc.lcd_enabled && ((self.stat.lyc_interrupt && coincidence) || mode_condition);
+        if stat_line && !self.stat_line {
+            interrupt_flag.request(Interrupt::LCDStat);
+        }
+        self.stat_line = stat_line;
+    }
+
+    pub fn read_register(&self, addr: usize) -> u8 {
+        match addr {
+            LCDC_ADDRESS => u8::from(self.lcdc),
+            STAT_ADDRESS => {
+                // Bit 7 is unused and reads as 1
+                let coincidence = (self.ly == self.lyc) as u8;
+                0b1000_0000
+                    | u8::from(self.stat)
+                    | coincidence << STAT_COINCIDENCE_BYTE_POSITION
+                    | u8::from(self.mode)
+            }
+            SCY_ADDRESS => self.scy,
+            SCX_ADDRESS => self.scx,
+            LY_ADDRESS => self.ly,
+            LYC_ADDRESS => self.lyc,
+            BGP_ADDRESS => self.bgp,
+            OBP0_ADDRESS => self.obp0,
+            OBP1_ADDRESS => self.obp1,
+            WY_ADDRESS => self.wy,
+            WX_ADDRESS => self.wx,
+            // CGB registers read as 0xFF outside CGB mode. Only bit 0 of VBK exists.
+            VBK_ADDRESS if self.cgb_mode => 0b1111_1110 | self.vram_bank as u8,
+            BCPS_ADDRESS if self.cgb_mode => self.bg_palettes.read_specification(),
+            BCPD_ADDRESS if self.cgb_mode => self.bg_palettes.read_data(),
+            OCPS_ADDRESS if self.cgb_mode => self.obj_palettes.read_specification(),
+            OCPD_ADDRESS if self.cgb_mode => self.obj_palettes.read_data(),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, addr: usize, value: u8, interrupt_flag: &mut InterruptFlags) {
+        match addr {
+            LCDC_ADDRESS => {
+                let lcdc = LCDControl::from(value);
+                if lcdc.lcd_enabled != self.lcdc.lcd_enabled {
+                    // Switching the LCD either way restarts it at line 0
+                    self.line = 0;
+                    self.ly = 0;
+                    self.line_dots = 0;
+                    self.mode = Mode::HBlank;
+                    self.transfer_done = false;
+                    self.first_line_after_enable = lcdc.lcd_enabled;
+                    self.start_frame();
+                }
+                self.lcdc = lcdc;
+            }
+            STAT_ADDRESS => self.stat = LCDStatus::from(value),
+            SCY_ADDRESS => self.scy = value,
+            SCX_ADDRESS => self.scx = value,
+            // LY is read-only
+            LY_ADDRESS => {}
+            LYC_ADDRESS => self.lyc = value,
+            BGP_ADDRESS => self.bgp = value,
+            OBP0_ADDRESS => self.obp0 = value,
+            OBP1_ADDRESS => self.obp1 = value,
+            WY_ADDRESS => self.wy = value,
+            WX_ADDRESS => self.wx = value,
+            VBK_ADDRESS if self.cgb_mode => self.vram_bank = (value & 0b1) as usize,
+            BCPS_ADDRESS if self.cgb_mode => self.bg_palettes.write_specification(value),
+            BCPD_ADDRESS if self.cgb_mode => self.bg_palettes.write_data(value),
+            OCPS_ADDRESS if self.cgb_mode => self.obj_palettes.write_specification(value),
+            OCPD_ADDRESS if self.cgb_mode => self.obj_palettes.write_data(value),
+            _ => {}
+        }
+        self.update_stat_line(interrupt_flag);
+    }
+
+    // Tile decoded from 0x8000 + 16 * `index`; indices from 384 on are in
+    // VRAM bank 1
+    pub fn tile(&self, index: usize) -> &Tile {
+        &self.tile_set[index]
+    }
+
+    // Tile number at column `x`, row `y` of the 0x9800 or 0x9C00 tile map
+    pub fn tile_map_entry(&self, high_map: bool, x: usize, y: usize) -> u8 {
+        self.vram[0][tile_map_address(high_map, x, y)]
+    }
+
+    // CGB attributes of the same tile map entry
+    pub fn tile_map_attributes(&self, high_map: bool, x: usize, y: usize) -> TileAttributes {
+        TileAttributes::from(self.vram[1][tile_map_address(high_map, x, y)])
+    }
+
+    // Outside CGB mode VBK is locked to bank 0
+    pub fn set_cgb_mode(&mut self, cgb_mode: bool) {
+        self.cgb_mode = cgb_mode;
+        if !cgb_mode {
+            self.vram_bank = 0;
+        }
+    }
+
+    // The CPU is locked out of VRAM while the PPU draws
+    pub fn vram_accessible(&self) -> bool {
+        self.mode != Mode::PixelTransfer
+    }
+
+    // ...and out of OAM while the PPU scans or draws
+    pub fn oam_accessible(&self) -> bool {
+        !matches!(self.mode, Mode::OAMScan | Mode::PixelTransfer)
+    }
+
+    // DMG OAM corruption bug: a 16-bit increment or decrement of a value in
+    // 0xFE00-0xFEFF during OAM scan garbles the row the PPU is reading, mixing
+    // in the row before it. The first row is never affected.
+    pub fn corrupt_oam(&mut self) {
+        if self.mode != Mode::OAMScan {
+            return;
+        }
+        let row = (self.line_dots as usize / DOTS_PER_CYCLE as usize) * OAM_ROW_SIZE;
+        if row == 0 || row >= OAM_SIZE {
+            return;
+        }
+        let previous = row - OAM_ROW_SIZE;
+        let word = |addr: usize| u16::from_le_bytes([self.oam[addr], self.oam[addr + 1]]);
+        let (a, b, c) = (word(row), word(previous), word(previous + 4));
+        let first = ((a ^ c) & (b ^ c)) ^ c;
+        self.oam[row..row + 2].copy_from_slice(&first.to_le_bytes());
+        self.oam
+            .copy_within(previous + 2..previous + OAM_ROW_SIZE, row + 2);
+    }
+
     pub fn read_vram(&self, addr: usize) -> u8 {
-        self.vram[addr]
+        self.vram[self.vram_bank][addr]
+    }
+
+    pub fn read_oam(&self, addr: usize) -> u8 {
+        self.oam[addr]
+    }
+
+    pub fn write_oam(&mut self, addr: usize, value: u8) {
+        self.oam[addr] = value;
     }
 
     pub fn write_vram(&mut self, addr: usize, value: u8) {
-        self.vram[addr] = value;
+        let bank = self.vram_bank;
+        self.vram[bank][addr] = value;
 
-        // If idx > 0x1800, we're not writing to the tile set storage
-        if addr > 0x1800 {
+        // If idx >= 0x1800, we're not writing to the tile set storage
+        if addr >= TILE_SET_END {
             return;
         }
 
@@ -46,10 +725,10 @@ impl GPU {
         let norm_idx = addr & 0xFFFE;
 
         // 2 bytes encoding tile row
-        let byte1 = self.vram[norm_idx];
-        let byte2 = self.vram[norm_idx + 1];
+        let byte1 = self.vram[bank][norm_idx];
+        let byte2 = self.vram[bank][norm_idx + 1];
 
-        let tile_idx = addr / 16;
+        let tile_idx = bank * TILE_COUNT + addr / 16;
         let row_idx = (addr % 16) / 2;
 
         for pixel_idx in 0..8 {
@@ -57,7 +736,7 @@ impl GPU {
             let lsb = byte1 & mask;
             let msb = byte2 & mask;
 
-            let value = match (lsb != 0, msb != 1) {
+            let value = match (lsb != 0, msb != 0) {
                 (true, true) => TilePixelValue::Three,
                 (false, true) => TilePixelValue::Two,
                 (true, false) => TilePixelValue::One,
@@ -68,3 +747,204 @@ impl GPU {
         }
     }
 }
+
+impl Default for GPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_tile_row(gpu: &mut GPU, tile: usize, row: usize, low: u8, high: u8) {
+        gpu.write_vram(tile * 16 + row * 2, low);
+        gpu.write_vram(tile * 16 + row * 2 + 1, high);
+    }
+
+    fn write_sprite(gpu: &mut GPU, index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+        for (offset, value) in [y, x, tile, attributes].into_iter().enumerate() {
+            gpu.write_oam(index * SPRITE_SIZE + offset, value);
+        }
+    }
+
+    // Length in dots of mode 3 on line 10, drawn by the pixel FIFO
+    fn pixel_transfer_dots(gpu: &mut GPU) -> u16 {
+        let mut interrupt_flag = InterruptFlags::new();
+        gpu.set_renderer(Renderer::PixelFifo);
+        gpu.write_register(LCDC_ADDRESS, 0x83, &mut interrupt_flag);
+        run_to(gpu, &mut interrupt_flag, 10, Mode::PixelTransfer);
+        let start = gpu.line_dots;
+        run_to(gpu, &mut interrupt_flag, 10, Mode::HBlank);
+        gpu.line_dots - start
+    }
+
+    // Ticks dot by dot until `line` enters `mode`
+    fn run_to(gpu: &mut GPU, interrupt_flag: &mut InterruptFlags, line: u8, mode: Mode) {
+        while !(gpu.line == line && gpu.mode == mode) {
+            gpu.tick(interrupt_flag);
+        }
+    }
+
+    #[test]
+    fn decodes_each_pixel_value() {
+        let mut gpu = GPU::new();
+        write_tile_row(&mut gpu, 0, 0, 0x00, 0x00);
+        write_tile_row(&mut gpu, 0, 1, 0xFF, 0x00);
+        write_tile_row(&mut gpu, 0, 2, 0x00, 0xFF);
+        write_tile_row(&mut gpu, 0, 3, 0xFF, 0xFF);
+
+        let tile = gpu.tile(0);
+        assert_eq!(tile[0], [TilePixelValue::Zero; 8]);
+        assert_eq!(tile[1], [TilePixelValue::One; 8]);
+        assert_eq!(tile[2], [TilePixelValue::Two; 8]);
+        assert_eq!(tile[3], [TilePixelValue::Three; 8]);
+    }
+
+    #[test]
+    fn decodes_mixed_bits_left_to_right() {
+        let mut gpu = GPU::new();
+        write_tile_row(&mut gpu, 5, 4, 0b1010_0101, 0b1100_0011);
+
+        use TilePixelValue::*;
+        assert_eq!(
+            gpu.tile(5)[4],
+            [Three, Two, One, Zero, Zero, One, Two, Three]
+        );
+    }
+
+    #[test]
+    fn either_byte_of_a_row_updates_the_tile() {
+        let mut gpu = GPU::new();
+        gpu.write_vram(0x0011, 0x80);
+        assert_eq!(gpu.tile(1)[0][0], TilePixelValue::Two);
+        gpu.write_vram(0x0010, 0x80);
+        assert_eq!(gpu.tile(1)[0][0], TilePixelValue::Three);
+        gpu.write_vram(0x0011, 0x00);
+        assert_eq!(gpu.tile(1)[0][0], TilePixelValue::One);
+    }
+
+    #[test]
+    fn decodes_first_and_last_tile_bytes() {
+        let mut gpu = GPU::new();
+        gpu.write_vram(0x0000, 0x01);
+        gpu.write_vram(0x17FF, 0x80);
+
+        assert_eq!(gpu.tile(0)[0][7], TilePixelValue::One);
+        assert_eq!(gpu.tile(TILE_COUNT - 1)[7][0], TilePixelValue::Two);
+    }
+
+    #[test]
+    fn decodes_tiles_in_the_second_vram_bank() {
+        let mut gpu = GPU::new();
+        let mut interrupt_flag = InterruptFlags::new();
+        gpu.write_register(VBK_ADDRESS, 1, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(VBK_ADDRESS), 0xFF);
+
+        gpu.set_cgb_mode(true);
+        gpu.write_register(VBK_ADDRESS, 1, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(VBK_ADDRESS), 0xFF);
+        write_tile_row(&mut gpu, 0, 0, 0xFF, 0xFF);
+        gpu.write_vram(0x1FFF, 0b1010_1101);
+
+        assert_eq!(gpu.tile(0)[0], [TilePixelValue::Zero; 8]);
+        assert_eq!(gpu.tile(TILE_COUNT)[0], [TilePixelValue::Three; 8]);
+        let attributes = gpu.tile_map_attributes(true, 31, 31);
+        assert_eq!(u8::from(attributes), 0b1010_1101);
+        assert!(attributes.priority && attributes.x_flip && attributes.vram_bank);
+        assert_eq!(attributes.palette, 5);
+
+        gpu.write_register(VBK_ADDRESS, 0xFE, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(VBK_ADDRESS), 0xFE);
+        assert_eq!(gpu.read_vram(0x0000), 0x00);
+    }
+
+    #[test]
+    fn palette_data_writes_auto_increment() {
+        let mut gpu = GPU::new();
+        let mut interrupt_flag = InterruptFlags::new();
+        gpu.write_register(BCPS_ADDRESS, 0x80, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(BCPS_ADDRESS), 0xFF);
+
+        gpu.set_cgb_mode(true);
+        gpu.write_register(BCPS_ADDRESS, 0x80 | 0x3E, &mut interrupt_flag);
+        gpu.write_register(BCPD_ADDRESS, 0x1F, &mut interrupt_flag);
+        gpu.write_register(BCPD_ADDRESS, 0xFC, &mut interrupt_flag);
+        gpu.write_register(BCPD_ADDRESS, 0x00, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(BCPS_ADDRESS), 0xC1);
+        assert_eq!(gpu.read_register(BCPD_ADDRESS), 0xFF);
+        assert_eq!(gpu.bg_palettes.color(7, TilePixelValue::Three), 0x7C1F);
+        assert_eq!(gpu.bg_palettes.color(0, TilePixelValue::Zero), 0x7F00);
+
+        gpu.write_register(OCPS_ADDRESS, 0x02, &mut interrupt_flag);
+        gpu.write_register(OCPD_ADDRESS, 0x12, &mut interrupt_flag);
+        gpu.write_register(OCPD_ADDRESS, 0x34, &mut interrupt_flag);
+        assert_eq!(gpu.read_register(OCPS_ADDRESS), 0x42);
+        assert_eq!(gpu.read_register(OCPD_ADDRESS), 0x34);
+    }
+
+    #[test]
+    fn tile_map_writes_leave_tiles_alone() {
+        let mut gpu = GPU::new();
+        gpu.write_vram(0x1800, 0xFF);
+        gpu.write_vram(0x1801, 0xFF);
+        gpu.write_vram(0x1BFF, 0x12);
+        gpu.write_vram(0x1C00, 0x34);
+        gpu.write_vram(0x1FFF, 0x56);
+
+        assert_eq!(*gpu.tile(TILE_COUNT - 1), empty_tile());
+        assert_eq!(gpu.tile_map_entry(false, 0, 0), 0xFF);
+        assert_eq!(gpu.tile_map_entry(false, 1, 0), 0xFF);
+        assert_eq!(gpu.tile_map_entry(false, 31, 31), 0x12);
+        assert_eq!(gpu.tile_map_entry(true, 0, 0), 0x34);
+        assert_eq!(gpu.tile_map_entry(true, 31, 31), 0x56);
+    }
+
+    #[test]
+    fn sprite_size_change_mid_line_keeps_rows_in_range() {
+        let mut gpu = GPU::new();
+        let mut interrupt_flag = InterruptFlags::new();
+        gpu.set_renderer(Renderer::PixelFifo);
+        write_tile_row(&mut gpu, 4, 5, 0xFF, 0xFF);
+        // Y-flipped 8x16 sprite showing its row 10 on line 10
+        write_sprite(&mut gpu, 0, 16, 88, 4, 0b0100_0000);
+        gpu.write_register(OBP0_ADDRESS, 0xE4, &mut interrupt_flag);
+        gpu.write_register(LCDC_ADDRESS, 0x87, &mut interrupt_flag);
+        run_to(&mut gpu, &mut interrupt_flag, 10, Mode::PixelTransfer);
+
+        // Back to 8x8 before the sprite is fetched: row 10 becomes row 2,
+        // flipped to row 5
+        gpu.write_register(LCDC_ADDRESS, 0x83, &mut interrupt_flag);
+        run_to(&mut gpu, &mut interrupt_flag, 10, Mode::HBlank);
+        let row = 10 * SCREEN_WIDTH;
+        assert_eq!(gpu.framebuffer()[row + 80], u16::from(Shade::Black));
+        assert_eq!(gpu.framebuffer()[row + 88], u16::from(Shade::White));
+    }
+
+    #[test]
+    fn sprites_lengthen_pixel_transfer() {
+        let base = pixel_transfer_dots(&mut GPU::new());
+        assert_eq!(base, 172);
+
+        // A sprite at X=0 costs 11 dots like one at the screen's left edge,
+        // and so does a sprite on the first pixel of the next tile
+        for x in [0, 8, 16] {
+            let mut gpu = GPU::new();
+            write_sprite(&mut gpu, 0, 16 + 10, x, 0, 0);
+            assert_eq!(pixel_transfer_dots(&mut gpu), base + 11, "X={}", x);
+        }
+
+        // Later in a tile, the background fetch is closer to done
+        let mut gpu = GPU::new();
+        write_sprite(&mut gpu, 0, 16 + 10, 12, 0, 0);
+        assert_eq!(pixel_transfer_dots(&mut gpu), base + 7);
+
+        // Once the background fetch is done, more sprites only add their own fetch
+        let mut gpu = GPU::new();
+        write_sprite(&mut gpu, 0, 16 + 10, 0, 0, 0);
+        write_sprite(&mut gpu, 1, 16 + 10, 8, 0, 0);
+        write_sprite(&mut gpu, 2, 16 + 10, 8, 0, 0);
+        assert_eq!(pixel_transfer_dots(&mut gpu), base + 11 + 6 + 6);
+    }
+}
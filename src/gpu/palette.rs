@@ -0,0 +1,65 @@
+use super::TilePixelValue;
+
+// 8 palettes of 4 colors, 2 bytes per color
+const PALETTE_RAM_SIZE: usize = 64;
+const BYTES_PER_PALETTE: usize = 8;
+const AUTO_INCREMENT_BYTE_POSITION: u8 = 7;
+const INDEX_MASK: u8 = 0b0011_1111;
+const RGB555_MASK: u16 = 0x7FFF;
+
+// CGB palette RAM behind a specification register (BCPS/OCPS), which picks
+// the byte, and a data register (BCPD/OCPD) that reads and writes it. Colors
+// are little-endian RGB555, red in the low bits.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ColorPalettes {
+    data: [u8; PALETTE_RAM_SIZE],
+    index: u8,
+    auto_increment: bool, // step the index after each data write
+}
+
+impl ColorPalettes {
+    // Starts out white, as the boot ROM leaves the background palettes
+    pub fn new() -> Self {
+        ColorPalettes {
+            data: [0xFF; PALETTE_RAM_SIZE],
+            index: 0,
+            auto_increment: false,
+        }
+    }
+
+    pub fn read_specification(&self) -> u8 {
+        // Bit 6 is unused and reads as 1
+        (if self.auto_increment { 1 } else { 0 }) << AUTO_INCREMENT_BYTE_POSITION
+            | 0b0100_0000
+            | self.index
+    }
+
+    pub fn write_specification(&mut self, value: u8) {
+        self.auto_increment = ((value >> AUTO_INCREMENT_BYTE_POSITION) & 0b1) != 0;
+        self.index = value & INDEX_MASK;
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[self.index as usize]
+    }
+
+    // Reads never advance the index, only writes do
+    pub fn write_data(&mut self, value: u8) {
+        self.data[self.index as usize] = value;
+        if self.auto_increment {
+            self.index = (self.index + 1) & INDEX_MASK;
+        }
+    }
+
+    // RGB555 color `value` of palette `palette` (0-7)
+    pub fn color(&self, palette: u8, value: TilePixelValue) -> u16 {
+        let addr = palette as usize * BYTES_PER_PALETTE + value.palette_index() as usize * 2;
+        u16::from_le_bytes([self.data[addr], self.data[addr + 1]]) & RGB555_MASK
+    }
+}
+
+impl Default for ColorPalettes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
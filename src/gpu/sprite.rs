@@ -0,0 +1,75 @@
+const BEHIND_BACKGROUND_BYTE_POSITION: u8 = 7;
+const Y_FLIP_BYTE_POSITION: u8 = 6;
+const X_FLIP_BYTE_POSITION: u8 = 5;
+const PALETTE_BYTE_POSITION: u8 = 4;
+const VRAM_BANK_BYTE_POSITION: u8 = 3;
+const CGB_PALETTE_MASK: u8 = 0b111;
+
+// OAM entries are 4 bytes: Y, X, tile number, attributes
+pub const SPRITE_SIZE: usize = 4;
+// Y and X are stored offset so sprites can be partially off the top/left
+pub const SPRITE_Y_OFFSET: u8 = 16;
+pub const SPRITE_X_OFFSET: u8 = 8;
+
+// Attribute byte of an OAM entry
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteAttributes {
+    pub behind_background: bool, // background colors 1-3 draw over the sprite
+    pub y_flip: bool,
+    pub x_flip: bool,
+    pub palette: bool, // OBP1 instead of OBP0
+    // CGB mode only
+    pub vram_bank: bool, // tile data comes from bank 1
+    pub cgb_palette: u8, // object palette 0-7
+}
+
+impl std::convert::From<SpriteAttributes> for u8 {
+    fn from(attributes: SpriteAttributes) -> Self {
+        (if attributes.behind_background { 1 } else { 0 }) << BEHIND_BACKGROUND_BYTE_POSITION
+            | (if attributes.y_flip { 1 } else { 0 }) << Y_FLIP_BYTE_POSITION
+            | (if attributes.x_flip { 1 } else { 0 }) << X_FLIP_BYTE_POSITION
+            | (if attributes.palette { 1 } else { 0 }) << PALETTE_BYTE_POSITION
+            | (if attributes.vram_bank { 1 } else { 0 }) << VRAM_BANK_BYTE_POSITION
+            | attributes.cgb_palette & CGB_PALETTE_MASK
+    }
+}
+
+impl std::convert::From<u8> for SpriteAttributes {
+    fn from(byte: u8) -> Self {
+        SpriteAttributes {
+            behind_background: ((byte >> BEHIND_BACKGROUND_BYTE_POSITION) & 0b1) != 0,
+            y_flip: ((byte >> Y_FLIP_BYTE_POSITION) & 0b1) != 0,
+            x_flip: ((byte >> X_FLIP_BYTE_POSITION) & 0b1) != 0,
+            palette: ((byte >> PALETTE_BYTE_POSITION) & 0b1) != 0,
+            vram_bank: ((byte >> VRAM_BANK_BYTE_POSITION) & 0b1) != 0,
+            cgb_palette: byte & CGB_PALETTE_MASK,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprite {
+    pub y: u8, // screen Y + 16
+    pub x: u8, // screen X + 8
+    pub tile: u8,
+    pub attributes: SpriteAttributes,
+}
+
+impl Sprite {
+    // Decodes the 4-byte OAM entry at the start of `bytes`
+    pub fn from_oam(bytes: &[u8]) -> Self {
+        Sprite {
+            y: bytes[0],
+            x: bytes[1],
+            tile: bytes[2],
+            attributes: SpriteAttributes::from(bytes[3]),
+        }
+    }
+
+    // Whether the sprite covers `line`, given the sprite height
+    pub fn on_line(&self, line: u8, height: u8) -> bool {
+        let top = self.y as i16 - SPRITE_Y_OFFSET as i16;
+        let line = line as i16;
+        line >= top && line < top + height as i16
+    }
+}
@@ -0,0 +1,55 @@
+const PRIORITY_BYTE_POSITION: u8 = 7;
+const Y_FLIP_BYTE_POSITION: u8 = 6;
+const X_FLIP_BYTE_POSITION: u8 = 5;
+const VRAM_BANK_BYTE_POSITION: u8 = 3;
+const PALETTE_MASK: u8 = 0b111;
+
+// CGB attributes of a tile map entry, stored at the same address in VRAM bank 1
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TileAttributes {
+    pub priority: bool, // colors 1-3 draw over sprites
+    pub y_flip: bool,
+    pub x_flip: bool,
+    pub vram_bank: bool, // tile data comes from bank 1
+    pub palette: u8,     // background palette 0-7
+}
+
+impl TileAttributes {
+    pub fn new() -> Self {
+        TileAttributes {
+            priority: false,
+            y_flip: false,
+            x_flip: false,
+            vram_bank: false,
+            palette: 0,
+        }
+    }
+}
+
+impl Default for TileAttributes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::convert::From<TileAttributes> for u8 {
+    fn from(attributes: TileAttributes) -> Self {
+        (if attributes.priority { 1 } else { 0 }) << PRIORITY_BYTE_POSITION
+            | (if attributes.y_flip { 1 } else { 0 }) << Y_FLIP_BYTE_POSITION
+            | (if attributes.x_flip { 1 } else { 0 }) << X_FLIP_BYTE_POSITION
+            | (if attributes.vram_bank { 1 } else { 0 }) << VRAM_BANK_BYTE_POSITION
+            | attributes.palette & PALETTE_MASK
+    }
+}
+
+impl std::convert::From<u8> for TileAttributes {
+    fn from(byte: u8) -> Self {
+        TileAttributes {
+            priority: ((byte >> PRIORITY_BYTE_POSITION) & 0b1) != 0,
+            y_flip: ((byte >> Y_FLIP_BYTE_POSITION) & 0b1) != 0,
+            x_flip: ((byte >> X_FLIP_BYTE_POSITION) & 0b1) != 0,
+            vram_bank: ((byte >> VRAM_BANK_BYTE_POSITION) & 0b1) != 0,
+            palette: byte & PALETTE_MASK,
+        }
+    }
+}
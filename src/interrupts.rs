@@ -0,0 +1,160 @@
+pub const IF_ADDRESS: usize = 0xFF0F;
+pub const IE_ADDRESS: usize = 0xFFFF;
+
+const VBLANK_BYTE_POSITION: u8 = 0;
+const LCD_STAT_BYTE_POSITION: u8 = 1;
+const TIMER_BYTE_POSITION: u8 = 2;
+const SERIAL_BYTE_POSITION: u8 = 3;
+const JOYPAD_BYTE_POSITION: u8 = 4;
+
+// Interrupt sources, in priority order
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interrupt {
+    VBlank,
+    LCDStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    // Address the CPU jumps to when servicing the interrupt
+    pub fn vector(&self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x40,
+            Interrupt::LCDStat => 0x48,
+            Interrupt::Timer => 0x50,
+            Interrupt::Serial => 0x58,
+            Interrupt::Joypad => 0x60,
+        }
+    }
+}
+
+// Layout shared by the IE (enabled) and IF (requested) registers
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct InterruptFlags {
+    pub vblank: bool,
+    pub lcd_stat: bool,
+    pub timer: bool,
+    pub serial: bool,
+    pub joypad: bool,
+}
+
+impl InterruptFlags {
+    pub fn new() -> Self {
+        InterruptFlags {
+            vblank: false,
+            lcd_stat: false,
+            timer: false,
+            serial: false,
+            joypad: false,
+        }
+    }
+
+    pub fn set(&mut self, interrupt: Interrupt, value: bool) {
+        match interrupt {
+            Interrupt::VBlank => self.vblank = value,
+            Interrupt::LCDStat => self.lcd_stat = value,
+            Interrupt::Timer => self.timer = value,
+            Interrupt::Serial => self.serial = value,
+            Interrupt::Joypad => self.joypad = value,
+        }
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.set(interrupt, true);
+    }
+
+    pub fn clear(&mut self, interrupt: Interrupt) {
+        self.set(interrupt, false);
+    }
+
+    // Highest priority interrupt that is both requested (self) and enabled
+    pub fn pending(&self, enabled: InterruptFlags) -> Option<Interrupt> {
+        let pending = u8::from(*self) & u8::from(enabled);
+        [
+            Interrupt::VBlank,
+            Interrupt::LCDStat,
+            Interrupt::Timer,
+            Interrupt::Serial,
+            Interrupt::Joypad,
+        ]
+        .into_iter()
+        .enumerate()
+        .find(|(bit, _)| pending & (1 << bit) != 0)
+        .map(|(_, interrupt)| interrupt)
+    }
+}
+
+impl Default for InterruptFlags {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::convert::From<InterruptFlags> for u8 {
+    fn from(flags: InterruptFlags) -> Self {
+        (if flags.vblank { 1 } else { 0 }) << VBLANK_BYTE_POSITION
+            | (if flags.lcd_stat { 1 } else { 0 }) << LCD_STAT_BYTE_POSITION
+            | (if flags.timer { 1 } else { 0 }) << TIMER_BYTE_POSITION
+            | (if flags.serial { 1 } else { 0 }) << SERIAL_BYTE_POSITION
+            | (if flags.joypad { 1 } else { 0 }) << JOYPAD_BYTE_POSITION
+    }
+}
+
+impl std::convert::From<u8> for InterruptFlags {
+    fn from(byte: u8) -> Self {
+        InterruptFlags {
+            vblank: ((byte >> VBLANK_BYTE_POSITION) & 0b1) != 0,
+            lcd_stat: ((byte >> LCD_STAT_BYTE_POSITION) & 0b1) != 0,
+            timer: ((byte >> TIMER_BYTE_POSITION) & 0b1) != 0,
+            serial: ((byte >> SERIAL_BYTE_POSITION) & 0b1) != 0,
+            joypad: ((byte >> JOYPAD_BYTE_POSITION) & 0b1) != 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_picks_the_lowest_enabled_bit() {
+        let requested = InterruptFlags::from(0b0001_1111);
+        let order = [
+            (0b0001_1111, Some(Interrupt::VBlank)),
+            (0b0001_1110, Some(Interrupt::LCDStat)),
+            (0b0001_1100, Some(Interrupt::Timer)),
+            (0b0001_1000, Some(Interrupt::Serial)),
+            (0b0001_0000, Some(Interrupt::Joypad)),
+            (0b0000_0000, None),
+        ];
+        for (enabled, interrupt) in order {
+            assert_eq!(requested.pending(InterruptFlags::from(enabled)), interrupt);
+        }
+        // Enabled but not requested doesn't count
+        let requested = InterruptFlags::from(0b0000_0100);
+        assert_eq!(requested.pending(InterruptFlags::from(0b0001_1011)), None);
+    }
+
+    #[test]
+    fn vectors_follow_priority() {
+        let vectors = [
+            Interrupt::VBlank,
+            Interrupt::LCDStat,
+            Interrupt::Timer,
+            Interrupt::Serial,
+            Interrupt::Joypad,
+        ]
+        .map(|interrupt| interrupt.vector());
+        assert_eq!(vectors, [0x40, 0x48, 0x50, 0x58, 0x60]);
+    }
+
+    #[test]
+    fn unused_bits_are_dropped() {
+        let flags = InterruptFlags::from(0b1110_0101);
+        assert!(flags.vblank && flags.timer);
+        assert!(!flags.lcd_stat && !flags.serial && !flags.joypad);
+        assert_eq!(u8::from(flags), 0b0000_0101);
+    }
+}
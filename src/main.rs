@@ -1,4 +1,8 @@
+pub mod cartridge;
 pub mod cpu;
 pub mod gpu;
+pub mod interrupts;
+pub mod model;
+pub mod timer;
 
 fn main() {}
@@ -0,0 +1,159 @@
+use std::fmt;
+
+use crate::cartridge::header::{title_checksum, CgbSupport};
+use crate::cartridge::Cartridge;
+use crate::cpu::registers::Registers;
+
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+// The CGB boot ROM is split around the cartridge header at 0x0100-0x01FF
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+// Title checksums of the DMG games whose CGB palette setup leaves HL = 0x991A
+const SPECIAL_TITLE_CHECKSUMS: [u8; 2] = [0x43, 0x58];
+
+// I/O registers as every boot ROM leaves them, apart from SC
+const POST_BOOT_IO_REGISTERS: [(u16, u8); 36] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF01, 0x00), // SB
+    (0xFF05, 0x00), // TIMA
+    (0xFF06, 0x00), // TMA
+    (0xFF07, 0xF8), // TAC
+    (0xFF0F, 0xE1), // IF
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF21, 0x00), // NR42
+    (0xFF22, 0x00), // NR43
+    (0xFF23, 0xBF), // NR44
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF26, 0xF1), // NR52
+    (0xFF40, 0x91), // LCDC
+    (0xFF41, 0x85), // STAT
+    (0xFF42, 0x00), // SCY
+    (0xFF43, 0x00), // SCX
+    (0xFF45, 0x00), // LYC
+    (0xFF47, 0xFC), // BGP
+    (0xFF4A, 0x00), // WY
+    (0xFF4B, 0x00), // WX
+    (0xFFFF, 0x00), // IE
+];
+
+// Hardware revision being emulated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Model {
+    DMG, // original Game Boy
+    MGB, // Game Boy Pocket
+    CGB, // Game Boy Color
+}
+
+impl Model {
+    pub fn boot_rom_size(&self) -> usize {
+        match self {
+            Model::DMG | Model::MGB => DMG_BOOT_ROM_SIZE,
+            Model::CGB => CGB_BOOT_ROM_SIZE,
+        }
+    }
+
+    // CPU registers as the boot ROM leaves them when it jumps to 0x0100. Some
+    // depend on the cartridge header the boot ROM has just read.
+    pub fn post_boot_registers(&self, cartridge: Option<&Cartridge>) -> Registers {
+        let mut registers = Registers::new();
+        match self {
+            Model::DMG | Model::MGB => {
+                let a = if *self == Model::DMG { 0x01 } else { 0xFF };
+                // H and C are left over from the header checksum calculation
+                let header_checksum = cartridge.map_or(0xFF, |c| c.header.header_checksum);
+                let flags = if header_checksum == 0 { 0x80 } else { 0xB0 };
+                registers.set_af(a << 8 | flags);
+                registers.set_bc(0x0013);
+                registers.set_de(0x00D8);
+                registers.set_hl(0x014D);
+            }
+            Model::CGB => {
+                registers.set_af(0x1180);
+                let cgb_game = cartridge.is_some_and(|c| c.header.cgb_support != CgbSupport::None);
+                if cgb_game {
+                    registers.set_bc(0x0000);
+                    registers.set_de(0xFF56);
+                    registers.set_hl(0x000D);
+                } else {
+                    // The boot ROM picks a palette from the title of Nintendo's
+                    // own DMG games, leaving the checksum in B
+                    let title_checksum = cartridge
+                        .filter(|c| c.header.licensee_code() == "01")
+                        .map_or(0x00, |c| title_checksum(c.rom()));
+                    registers.b = title_checksum;
+                    registers.set_de(0x0008);
+                    if SPECIAL_TITLE_CHECKSUMS.contains(&title_checksum) {
+                        registers.set_hl(0x991A);
+                    } else {
+                        registers.set_hl(0x007C);
+                    }
+                }
+            }
+        }
+        registers
+    }
+
+    // System counter (DIV and its hidden low byte) when the boot ROM exits.
+    // On CGB the boot ROM's running time depends on the cartridge, so this
+    // is only typical.
+    pub fn post_boot_timer_counter(&self) -> u16 {
+        match self {
+            Model::DMG | Model::MGB => 0xABCC,
+            Model::CGB => 0x1EA0,
+        }
+    }
+
+    // I/O register writes that reproduce the state the boot ROM hands over
+    pub fn post_boot_io_registers(&self) -> Vec<(u16, u8)> {
+        // CGB exposes the serial clock speed bit in SC
+        let serial_control = match self {
+            Model::DMG | Model::MGB => 0x7E,
+            Model::CGB => 0x7F,
+        };
+        let mut registers = POST_BOOT_IO_REGISTERS.to_vec();
+        registers.push((0xFF02, serial_control));
+        registers
+    }
+}
+
+#[derive(Debug)]
+pub enum BootRomError {
+    InvalidSize {
+        model: Model,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootRomError::InvalidSize {
+                model,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{:?} boot ROM must be {} bytes, got {}",
+                model, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {}
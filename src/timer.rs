@@ -0,0 +1,210 @@
+use crate::interrupts::{Interrupt, InterruptFlags};
+
+pub const DIV_ADDRESS: usize = 0xFF04;
+pub const TIMA_ADDRESS: usize = 0xFF05;
+pub const TMA_ADDRESS: usize = 0xFF06;
+pub const TAC_ADDRESS: usize = 0xFF07;
+
+const TAC_ENABLE_BIT: u8 = 0b100;
+const TAC_CLOCK_SELECT_MASK: u8 = 0b11;
+
+// Clock cycles per machine cycle; the system counter runs at the clock rate
+const CLOCKS_PER_CYCLE: u16 = 4;
+
+// DIV, TIMA, TMA and TAC. TIMA counts falling edges of one bit of the 16-bit
+// system counter (whose upper byte is DIV), ANDed with the enable bit, so
+// resetting DIV or changing TAC can increment it too.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Timer {
+    counter: u16, // system counter
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    reload_pending: bool, // TIMA overflowed and reads 0 until the next cycle
+    reloading: bool,      // TMA is being copied into TIMA this cycle
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            reload_pending: false,
+            reloading: false,
+        }
+    }
+
+    // Sets the system counter directly, e.g. to where the boot ROM leaves it
+    pub fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    pub fn step(&mut self, cycles: u8, interrupt_flag: &mut InterruptFlags) {
+        for _ in 0..cycles {
+            self.tick(interrupt_flag);
+        }
+    }
+
+    fn tick(&mut self, interrupt_flag: &mut InterruptFlags) {
+        // An overflow reloads TIMA and requests the interrupt one cycle late
+        self.reloading = false;
+        if self.reload_pending {
+            self.reload_pending = false;
+            self.reloading = true;
+            self.tima = self.tma;
+            interrupt_flag.request(Interrupt::Timer);
+        }
+
+        let signal = self.signal();
+        self.counter = self.counter.wrapping_add(CLOCKS_PER_CYCLE);
+        self.detect_falling_edge(signal);
+    }
+
+    // Counter bit watched for the selected frequency, gated by the enable bit
+    fn signal(&self) -> bool {
+        let bit = match self.tac & TAC_CLOCK_SELECT_MASK {
+            0b00 => 9, // 4096 Hz
+            0b01 => 3, // 262144 Hz
+            0b10 => 5, // 65536 Hz
+            _ => 7,    // 16384 Hz
+        };
+        self.tac & TAC_ENABLE_BIT != 0 && self.counter & (1 << bit) != 0
+    }
+
+    fn detect_falling_edge(&mut self, old_signal: bool) {
+        if old_signal && !self.signal() {
+            let (tima, overflow) = self.tima.overflowing_add(1);
+            self.tima = tima;
+            self.reload_pending |= overflow;
+        }
+    }
+
+    pub fn read(&self, addr: usize) -> u8 {
+        match addr {
+            DIV_ADDRESS => (self.counter >> 8) as u8,
+            TIMA_ADDRESS => self.tima,
+            TMA_ADDRESS => self.tma,
+            // Unused upper bits read as 1
+            _ => self.tac | 0b1111_1000,
+        }
+    }
+
+    pub fn write(&mut self, addr: usize, value: u8) {
+        match addr {
+            // Any write clears the whole system counter
+            DIV_ADDRESS => {
+                let signal = self.signal();
+                self.counter = 0;
+                self.detect_falling_edge(signal);
+            }
+            // Ignored while TMA is being loaded; otherwise cancels a pending reload
+            TIMA_ADDRESS => {
+                if !self.reloading {
+                    self.tima = value;
+                    self.reload_pending = false;
+                }
+            }
+            TMA_ADDRESS => {
+                self.tma = value;
+                if self.reloading {
+                    self.tima = value;
+                }
+            }
+            _ => {
+                let signal = self.signal();
+                self.tac = value & (TAC_ENABLE_BIT | TAC_CLOCK_SELECT_MASK);
+                self.detect_falling_edge(signal);
+            }
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Enabled at 262144 Hz, so TIMA counts falling edges of counter bit 3
+    fn fast_timer(counter: u16) -> Timer {
+        let mut timer = Timer::new();
+        timer.write(TAC_ADDRESS, 0b101);
+        timer.set_counter(counter);
+        timer
+    }
+
+    #[test]
+    fn div_reset_can_increment_tima() {
+        let mut timer = fast_timer(0x0008);
+        timer.write(DIV_ADDRESS, 0x12);
+        assert_eq!(timer.read(DIV_ADDRESS), 0);
+        assert_eq!(timer.read(TIMA_ADDRESS), 1);
+
+        // No edge when the watched bit is already low
+        let mut timer = fast_timer(0x0004);
+        timer.write(DIV_ADDRESS, 0x12);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0);
+    }
+
+    #[test]
+    fn disabling_the_timer_can_increment_tima() {
+        let mut timer = fast_timer(0x0008);
+        timer.write(TAC_ADDRESS, 0b001);
+        assert_eq!(timer.read(TIMA_ADDRESS), 1);
+    }
+
+    // TIMA at 0xFF one cycle before bit 3 falls, TMA at 0x80
+    fn overflowing_timer(interrupt_flag: &mut InterruptFlags) -> Timer {
+        let mut timer = fast_timer(0x000C);
+        timer.write(TMA_ADDRESS, 0x80);
+        timer.write(TIMA_ADDRESS, 0xFF);
+        timer.step(1, interrupt_flag);
+        timer
+    }
+
+    #[test]
+    fn overflow_reloads_one_cycle_late() {
+        let mut interrupt_flag = InterruptFlags::new();
+        let mut timer = overflowing_timer(&mut interrupt_flag);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x00);
+        assert!(!interrupt_flag.timer);
+
+        timer.step(1, &mut interrupt_flag);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x80);
+        assert!(interrupt_flag.timer);
+    }
+
+    #[test]
+    fn tima_write_before_reload_cancels_it() {
+        let mut interrupt_flag = InterruptFlags::new();
+        let mut timer = overflowing_timer(&mut interrupt_flag);
+        timer.write(TIMA_ADDRESS, 0x42);
+        timer.step(1, &mut interrupt_flag);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x42);
+        assert!(!interrupt_flag.timer);
+    }
+
+    #[test]
+    fn tima_write_during_reload_is_ignored() {
+        let mut interrupt_flag = InterruptFlags::new();
+        let mut timer = overflowing_timer(&mut interrupt_flag);
+        timer.step(1, &mut interrupt_flag);
+        timer.write(TIMA_ADDRESS, 0x42);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x80);
+
+        // TMA written in the same cycle goes straight through to TIMA
+        timer.write(TMA_ADDRESS, 0x90);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x90);
+
+        // The cycle after, TIMA is writable again
+        timer.step(1, &mut interrupt_flag);
+        timer.write(TIMA_ADDRESS, 0x42);
+        assert_eq!(timer.read(TIMA_ADDRESS), 0x42);
+    }
+}